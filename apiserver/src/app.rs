@@ -13,9 +13,10 @@
 // limitations under the License.
 
 use crate::context::Context;
-use crate::{routes, swagger};
+use crate::{auth, errors, routes, swagger};
 
 use axum::http::StatusCode;
+use axum::middleware;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -26,9 +27,13 @@ use tower_http::trace::TraceLayer;
 pub async fn run(ctx: Arc<Context>) {
     let port = ctx.config.port;
 
-    // build our application with a route
-    let app = routes::build().merge(swagger::build()).with_state(ctx).layer((
+    // build our application with a route, the API documentation is public
+    let api = routes::build()
+        .route_layer(middleware::from_fn_with_state(ctx.clone(), auth::authorize))
+        .layer(middleware::from_fn_with_state(ctx.clone(), auth::authenticate));
+    let app = api.merge(swagger::build()).with_state(ctx).layer((
         TraceLayer::new_for_http(),
+        middleware::from_fn(errors::request_id),
         // Graceful shutdown will wait for outstanding requests to complete. Add a timeout so
         // requests don't hang forever.
         TimeoutLayer::with_status_code(StatusCode::REQUEST_TIMEOUT, Duration::from_secs(10)),
@@ -0,0 +1,394 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use amp_common::resource::Playbook;
+use amp_resources::error::Error as ResourceError;
+use amp_resources::playbook;
+use axum::extract::{MatchedPath, Path, Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::Method;
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Extension;
+use jsonwebtoken::jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::context::Context;
+use crate::errors::ApiError;
+
+/// The authenticated user of the request.
+#[derive(Clone, Debug)]
+pub struct User {
+    pub name: String,
+    pub roles: Vec<String>,
+    /// The admin can see all the playbooks.
+    pub admin: bool,
+}
+
+/// The roles granted to the users per playbook, each role can do all that the lower ones can.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// List and view the playbook and its actors, read the logs and events.
+    Viewer,
+    /// Also synchronize the sources, restart and rebuild the actors.
+    Developer,
+    /// Also update, delete, start and stop the playbook, delete, scale and roll back the actors.
+    Owner,
+}
+
+impl Role {
+    /// Returns the role of the user on the playbook, the owner is recorded when it's created,
+    /// the others are granted in the roles annotation.
+    pub fn of(playbook: &Playbook, user: &str) -> Option<Role> {
+        if playbook::is_owned_by(playbook, user) {
+            return Some(Role::Owner);
+        }
+
+        let grants = playbook::grants(playbook);
+        grants.get(user).and_then(|role| serde_json::from_value(Value::String(role.clone())).ok())
+    }
+
+    /// Returns the role required by the route, the unknown changes require the owner.
+    fn required(method: &Method, route: &str) -> Role {
+        const DEVELOPER_ROUTES: [&str; 4] = ["/sync", "/sync/manifest", "/actions/restart", "/actions/rebuild"];
+
+        if method == Method::GET {
+            return Role::Viewer;
+        }
+        if method == Method::POST && DEVELOPER_ROUTES.iter().any(|suffix| route.ends_with(suffix)) {
+            return Role::Developer;
+        }
+        Role::Owner
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Role::Viewer => "viewer",
+            Role::Developer => "developer",
+            Role::Owner => "owner",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Authenticates the bearer tokens, they are validated as JWT against the JWKS,
+/// or looked up in the static tokens.
+pub struct Authenticator {
+    keys: Option<JwkSet>,
+    issuer: Option<String>,
+    audience: Option<String>,
+    roles_claim: String,
+    tokens: HashMap<String, (String, Vec<String>)>,
+    admin_role: String,
+    disabled: bool,
+}
+
+impl Authenticator {
+    pub fn new(config: &Config) -> anyhow::Result<Self> {
+        let keys = match &config.auth_jwks_file {
+            Some(path) => Some(serde_json::from_slice(&fs::read(path)?)?),
+            None => None,
+        };
+        let tokens = match &config.auth_tokens_file {
+            Some(path) => parse_tokens(&fs::read_to_string(path)?),
+            None => HashMap::new(),
+        };
+        if config.auth_disabled {
+            warn!("The authentication is disabled, all requests are served as the admin!");
+        } else if keys.is_none() && tokens.is_empty() {
+            anyhow::bail!(
+                "Neither JWKS nor static tokens are configured, set AMP_AUTH_DISABLED=true to disable the authentication"
+            );
+        }
+
+        Ok(Authenticator {
+            keys,
+            issuer: config.auth_issuer.clone(),
+            audience: config.auth_audience.clone(),
+            roles_claim: config.auth_roles_claim.clone(),
+            tokens,
+            admin_role: config.auth_admin_role.clone(),
+            disabled: config.auth_disabled,
+        })
+    }
+
+    /// Returns the user of the bearer token, it's an anonymous admin if the authentication is disabled.
+    pub fn authenticate(&self, token: Option<&str>) -> Result<User, ApiError> {
+        if self.disabled {
+            return Ok(User { name: "anonymous".into(), roles: vec![], admin: true });
+        }
+
+        let token = token.ok_or(ApiError::Unauthorized)?;
+        let (name, roles) = match self.tokens.get(token) {
+            Some((name, roles)) => (name.clone(), roles.clone()),
+            None => self.validate(token)?,
+        };
+        let admin = roles.contains(&self.admin_role);
+
+        Ok(User { name, roles, admin })
+    }
+
+    /// Validate the JWT, and returns the subject and roles of it.
+    fn validate(&self, token: &str) -> Result<(String, Vec<String>), ApiError> {
+        let keys = self.keys.as_ref().ok_or(ApiError::Unauthorized)?;
+        let header = decode_header(token).map_err(invalid)?;
+
+        // The key id can be omitted only if there is only one key.
+        let jwk = match &header.kid {
+            Some(kid) => keys.find(kid),
+            None if keys.keys.len() == 1 => keys.keys.first(),
+            None => None,
+        };
+        let jwk = jwk.ok_or(ApiError::Unauthorized)?;
+
+        // The algorithm is decided by the key, never by the token.
+        let mut validation = Validation::new(algorithm(jwk).ok_or(ApiError::Unauthorized)?);
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        match &self.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+
+        let key = DecodingKey::from_jwk(jwk).map_err(invalid)?;
+        let claims = decode::<Value>(token, &key, &validation).map_err(invalid)?.claims;
+
+        let name = claims["sub"].as_str().filter(|sub| !sub.is_empty()).ok_or(ApiError::Unauthorized)?;
+        let roles = match &claims[&self.roles_claim] {
+            Value::Array(items) => items.iter().filter_map(Value::as_str).map(String::from).collect(),
+            Value::String(items) => items.split_whitespace().map(String::from).collect(),
+            _ => vec![],
+        };
+
+        Ok((name.to_string(), roles))
+    }
+}
+
+/// Authenticate the bearer token of the request, and keep the user in the request extensions.
+pub async fn authenticate(
+    State(ctx): State<Arc<Context>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let token = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    let user = ctx.authenticator.authenticate(token)?;
+    debug!("Authenticated user {} with roles {:?}", user.name, user.roles);
+    request.extensions_mut().insert(user);
+
+    Ok(next.run(request).await)
+}
+
+/// Only the users granted a role on the playbook or the admin can access the playbook and its actors,
+/// and the role must be enough for the route. The playbook of others is not found, so that its existence is not leaked.
+pub async fn authorize(
+    State(ctx): State<Arc<Context>>,
+    Extension(user): Extension<User>,
+    params: Option<Path<HashMap<String, String>>>,
+    route: MatchedPath,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let id = params.as_ref().and_then(|Path(params)| params.get("id").or(params.get("pid")));
+    if let (false, Some(id)) = (user.admin, id) {
+        let id = Uuid::parse_str(id).map_err(|_| ApiError::NotFound)?;
+        match playbook::get(&ctx.k8s, &id.to_string()).await {
+            Ok(playbook) => {
+                let role = Role::of(&playbook, &user.name).ok_or(ApiError::NotFound)?;
+                let required = Role::required(request.method(), route.as_str());
+                if role < required {
+                    debug!("User {} is {} on playbook {}, but {} is required", user.name, role, id, required);
+                    return Err(ApiError::Forbidden(required.to_string()));
+                }
+            }
+            // No role can be granted on a missing playbook, all the routes with it act on an existing one.
+            Err(ResourceError::KubeError(kube::Error::Api(err))) if err.code == 404 => return Err(ApiError::NotFound),
+            Err(err) => return Err(ApiError::ResourceError(err)),
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Returns the algorithm of the key, it's derived from the key type if not specified.
+fn algorithm(jwk: &Jwk) -> Option<Algorithm> {
+    if let Some(algorithm) = &jwk.common.key_algorithm {
+        let algorithm = serde_json::to_value(algorithm).ok()?;
+        return algorithm.as_str().and_then(|algorithm| Algorithm::from_str(algorithm).ok());
+    }
+
+    match &jwk.algorithm {
+        AlgorithmParameters::RSA(_) => Some(Algorithm::RS256),
+        AlgorithmParameters::EllipticCurve(params) => match params.curve {
+            EllipticCurve::P256 => Some(Algorithm::ES256),
+            EllipticCurve::P384 => Some(Algorithm::ES384),
+            _ => None,
+        },
+        AlgorithmParameters::OctetKeyPair(_) => Some(Algorithm::EdDSA),
+        AlgorithmParameters::OctetKey(_) => Some(Algorithm::HS256),
+    }
+}
+
+/// Parse the static tokens, each line is `token,user[,role...]`, the blank lines and `#` comments are skipped.
+fn parse_tokens(data: &str) -> HashMap<String, (String, Vec<String>)> {
+    data.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(',').map(str::trim);
+            let (token, user) = (fields.next()?, fields.next()?);
+            if token.is_empty() || user.is_empty() {
+                warn!("Skipped the invalid static token of user {:?}", user);
+                return None;
+            }
+            Some((token.to_string(), (user.to_string(), fields.map(String::from).collect())))
+        })
+        .collect()
+}
+
+#[inline]
+fn invalid(err: jsonwebtoken::errors::Error) -> ApiError {
+    debug!("Invalid bearer token: {}", err);
+    ApiError::Unauthorized
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use amp_common::resource::PlaybookSpec;
+    use kube::ResourceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_role_ordering() {
+        assert!(Role::Viewer < Role::Developer);
+        assert!(Role::Developer < Role::Owner);
+        assert_eq!([Role::Owner, Role::Viewer, Role::Developer].iter().max(), Some(&Role::Owner));
+    }
+
+    #[test]
+    fn test_role_required() {
+        let table = [
+            (Method::GET, "/v1/playbooks/{id}", Role::Viewer),
+            (Method::GET, "/v1/playbooks/{id}/events", Role::Viewer),
+            (Method::GET, "/v1/playbooks/{id}/actors", Role::Viewer),
+            (Method::GET, "/v1/actors/{pid}/{name}/logs", Role::Viewer),
+            (Method::GET, "/v1/actors/{pid}/{name}/sync/status", Role::Viewer),
+            (Method::POST, "/v1/actors/{pid}/{name}/sync", Role::Developer),
+            (Method::POST, "/v1/actors/{pid}/{name}/sync/manifest", Role::Developer),
+            (Method::POST, "/v1/actors/{pid}/{name}/actions/restart", Role::Developer),
+            (Method::POST, "/v1/actors/{pid}/{name}/actions/rebuild", Role::Developer),
+            (Method::POST, "/v1/actors/{pid}/{name}/actions/scale", Role::Owner),
+            (Method::POST, "/v1/actors/{pid}/{name}/actions/rollback", Role::Owner),
+            (Method::DELETE, "/v1/actors/{pid}/{name}", Role::Owner),
+            (Method::PATCH, "/v1/playbooks/{id}", Role::Owner),
+            (Method::DELETE, "/v1/playbooks/{id}", Role::Owner),
+            (Method::POST, "/v1/playbooks/{id}/actions/start", Role::Owner),
+            (Method::POST, "/v1/playbooks/{id}/actions/stop", Role::Owner),
+            // The developer routes are only for POST, the others need the owner.
+            (Method::DELETE, "/v1/actors/{pid}/{name}/sync", Role::Owner),
+            (Method::PUT, "/v1/actors/{pid}/{name}/actions/restart", Role::Owner),
+            (Method::POST, "/v1/actors/{pid}/{name}/sync/unknown", Role::Owner),
+        ];
+
+        for (method, route, role) in table {
+            assert_eq!(Role::required(&method, route), role, "{method} {route}");
+        }
+    }
+
+    #[test]
+    fn test_role_of() {
+        let mut playbook = Playbook::new("test", PlaybookSpec::default());
+        playbook::set_owner(&mut playbook, "alice");
+        let grants = BTreeMap::from([
+            ("bob".to_string(), "developer".to_string()),
+            ("carol".to_string(), "viewer".to_string()),
+            ("dave".to_string(), "superuser".to_string()),
+        ]);
+        playbook.annotations_mut().insert(playbook::ROLES_ANNOTATION.into(), serde_json::to_string(&grants).unwrap());
+
+        assert_eq!(Role::of(&playbook, "alice"), Some(Role::Owner));
+        assert_eq!(Role::of(&playbook, "bob"), Some(Role::Developer));
+        assert_eq!(Role::of(&playbook, "carol"), Some(Role::Viewer));
+        assert_eq!(Role::of(&playbook, "dave"), None);
+        assert_eq!(Role::of(&playbook, "eve"), None);
+    }
+
+    #[test]
+    fn test_parse_tokens() {
+        let lines = [
+            "# token,user[,role...]",
+            "",
+            "t1,alice,admin,developer",
+            "  t2 , bob ",
+            "t3",
+            ",carol,admin",
+            "t4,,admin",
+        ];
+        let tokens = parse_tokens(&lines.join("\n"));
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens["t1"], ("alice".to_string(), vec!["admin".to_string(), "developer".to_string()]));
+        assert_eq!(tokens["t2"], ("bob".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_algorithm() {
+        let jwk = |value: Value| serde_json::from_value::<Jwk>(value).unwrap();
+
+        let rsa = jwk(serde_json::json!({ "kty": "RSA", "n": "AQAB", "e": "AQAB" }));
+        assert_eq!(algorithm(&rsa), Some(Algorithm::RS256));
+
+        let rsa = jwk(serde_json::json!({ "kty": "RSA", "alg": "RS384", "n": "AQAB", "e": "AQAB" }));
+        assert_eq!(algorithm(&rsa), Some(Algorithm::RS384));
+
+        let ec = jwk(serde_json::json!({ "kty": "EC", "crv": "P-256", "x": "AQAB", "y": "AQAB" }));
+        assert_eq!(algorithm(&ec), Some(Algorithm::ES256));
+
+        let ec = jwk(serde_json::json!({ "kty": "EC", "crv": "P-384", "x": "AQAB", "y": "AQAB" }));
+        assert_eq!(algorithm(&ec), Some(Algorithm::ES384));
+
+        let ec = jwk(serde_json::json!({ "kty": "EC", "crv": "P-521", "x": "AQAB", "y": "AQAB" }));
+        assert_eq!(algorithm(&ec), None);
+
+        let okp = jwk(serde_json::json!({ "kty": "OKP", "crv": "Ed25519", "x": "AQAB" }));
+        assert_eq!(algorithm(&okp), Some(Algorithm::EdDSA));
+
+        let oct = jwk(serde_json::json!({ "kty": "oct", "k": "c2VjcmV0" }));
+        assert_eq!(algorithm(&oct), Some(Algorithm::HS256));
+    }
+}
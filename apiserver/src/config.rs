@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::PathBuf;
+
 /// The configuration parameters for the application.
 ///
 /// These can either be passed on the command line, or pulled from environment variables.
@@ -35,4 +37,38 @@ pub struct Config {
     /// The NATS URL.
     #[clap(long, env = "AMP_NATS_URL")]
     pub nats_url: String,
+
+    /// The name of the Kubernetes namespace that Amphitheatre is
+    /// currently running in, the default is `amp-system`
+    #[clap(long, env = "AMP_NAMESPACE", default_value = "amp-system")]
+    pub namespace: String,
+
+    /// The JWKS file to validate the JWT bearer tokens.
+    #[clap(long, env = "AMP_AUTH_JWKS_FILE")]
+    pub auth_jwks_file: Option<PathBuf>,
+
+    /// The expected issuer (`iss`) of the JWT bearer tokens, not checked if it's not set.
+    #[clap(long, env = "AMP_AUTH_ISSUER")]
+    pub auth_issuer: Option<String>,
+
+    /// The expected audience (`aud`) of the JWT bearer tokens, not checked if it's not set.
+    #[clap(long, env = "AMP_AUTH_AUDIENCE")]
+    pub auth_audience: Option<String>,
+
+    /// The claim of the JWT bearer tokens carrying the roles of user, the default is `roles`.
+    #[clap(long, env = "AMP_AUTH_ROLES_CLAIM", default_value = "roles")]
+    pub auth_roles_claim: String,
+
+    /// The static bearer tokens file, each line is `token,user[,role...]`.
+    #[clap(long, env = "AMP_AUTH_TOKENS_FILE")]
+    pub auth_tokens_file: Option<PathBuf>,
+
+    /// The role of users who can see all the playbooks, the default is `admin`.
+    #[clap(long, env = "AMP_AUTH_ADMIN_ROLE", default_value = "admin")]
+    pub auth_admin_role: String,
+
+    /// Serve all requests as the admin without authentication, for local development only.
+    /// The apiserver refuses to start if neither JWKS nor static tokens are configured otherwise.
+    #[clap(long, env = "AMP_AUTH_DISABLED")]
+    pub auth_disabled: bool,
 }
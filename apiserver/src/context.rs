@@ -12,8 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::Arc;
+
 use kube::Client;
 
+use crate::auth::Authenticator;
 use crate::config::Config;
 
 /// The core type through which handler functions can access common API state.
@@ -28,10 +31,12 @@ use crate::config::Config;
 pub struct Context {
     pub config: Config,
     pub k8s: Client,
+    pub authenticator: Arc<Authenticator>,
 }
 
 impl Context {
     pub async fn new(config: Config) -> anyhow::Result<Context> {
-        Ok(Context { config, k8s: Client::try_default().await? })
+        let authenticator = Arc::new(Authenticator::new(&config)?);
+        Ok(Context { config, k8s: Client::try_default().await?, authenticator })
     }
 }
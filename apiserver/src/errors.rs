@@ -12,12 +12,25 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::extract::Request;
+use axum::http::header::HeaderName;
+use axum::http::{HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use thiserror::Error;
-use tracing::error;
+use tracing::{error, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The header carrying the request id, it's generated if the client doesn't send one.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
 
 #[derive(Debug, Error)]
 pub enum ApiError {
@@ -33,6 +46,21 @@ pub enum ApiError {
     #[error("Not Found")]
     NotFound,
 
+    #[error("Unauthorized: the bearer token is missing or invalid")]
+    Unauthorized,
+
+    #[error("Forbidden: the {0} role is required")]
+    Forbidden(String),
+
+    #[error("Conflict: the resource has been changed by others")]
+    Conflict,
+
+    #[error("Precondition Failed: the resource version does not match")]
+    PreconditionFailed,
+
+    #[error("Validation Error: {0}")]
+    ValidationError(String),
+
     #[error("Resolve Error")]
     ResolveError,
 
@@ -43,19 +71,210 @@ pub enum ApiError {
     ResourceError(#[source] amp_resources::error::Error),
 }
 
+/// The body of all the error responses.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    /// The stable machine readable code of the error, e.g. `not_found`, `conflict`,
+    /// `validation_failed`, clients should rely on it rather than the message.
+    pub code: String,
+    /// The human readable description of the error.
+    pub message: String,
+    /// The additional information of the error, e.g. the reason given by Kubernetes.
+    pub details: Option<Value>,
+    /// The id of the request, also returned in the `x-request-id` header.
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    /// Returns the status, the stable code and the details of the error. The Kubernetes API
+    /// errors are classified by their status, so a missing or changed resource is reported
+    /// as such instead of a server error.
+    fn parts(&self) -> (StatusCode, &'static str, Option<Value>) {
+        if let Some((code, details)) = self.kube_status() {
+            let details = Some(details);
+            match code {
+                404 => return (StatusCode::NOT_FOUND, "not_found", details),
+                409 => return (StatusCode::CONFLICT, "conflict", details),
+                422 => return (StatusCode::UNPROCESSABLE_ENTITY, "validation_failed", details),
+                _ => {}
+            }
+        }
+
+        match self {
+            Self::DatabaseError => (StatusCode::INTERNAL_SERVER_ERROR, "database_error", None),
+            Self::KubernetesError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "kubernetes_error", None),
+            Self::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", None),
+            Self::NotFound => (StatusCode::NOT_FOUND, "not_found", None),
+            Self::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized", None),
+            Self::Forbidden(role) => (StatusCode::FORBIDDEN, "forbidden", Some(json!({ "required_role": role }))),
+            Self::Conflict => (StatusCode::CONFLICT, "conflict", None),
+            Self::PreconditionFailed => (StatusCode::PRECONDITION_FAILED, "precondition_failed", None),
+            Self::ValidationError(reason) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, "validation_failed", Some(json!({ "reason": reason })))
+            }
+            Self::ResolveError => (StatusCode::INTERNAL_SERVER_ERROR, "resolve_failed", None),
+            Self::NatsError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "nats_error", None),
+            Self::ResourceError(_) => (StatusCode::INTERNAL_SERVER_ERROR, "resource_error", None),
+        }
+    }
+
+    /// The status code and the reason returned by the Kubernetes API, if the error comes from it.
+    fn kube_status(&self) -> Option<(u16, Value)> {
+        let status = match self {
+            Self::KubernetesError(kube::Error::Api(status)) => status,
+            Self::ResourceError(amp_resources::error::Error::KubeError(kube::Error::Api(status))) => status,
+            _ => return None,
+        };
+
+        Some((status.code, json!({ "reason": status.reason, "message": status.message })))
+    }
+}
+
 impl IntoResponse for ApiError {
-    fn into_response(self) -> axum::response::Response {
-        let (status, message) = match self {
-            Self::DatabaseError => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Self::KubernetesError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Self::InternalServerError => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Self::NotFound => (StatusCode::NOT_FOUND, self.to_string()),
-            Self::ResolveError => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Self::NatsError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
-            Self::ResourceError(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
+    fn into_response(self) -> Response {
+        let (status, code, details) = self.parts();
+        let message = match (status, self.kube_status()) {
+            (StatusCode::NOT_FOUND, Some(_)) => ApiError::NotFound.to_string(),
+            (StatusCode::CONFLICT, Some(_)) => ApiError::Conflict.to_string(),
+            _ => self.to_string(),
         };
+        let request_id = REQUEST_ID.try_with(Clone::clone).ok();
+
+        if status.is_server_error() {
+            error!("{} - {} ({:?})", status, self, request_id);
+        } else {
+            warn!("{} - {} ({:?})", status, self, request_id);
+        }
+
+        let body = ErrorResponse { code: code.to_string(), message, details, request_id };
+        (status, Json(body)).into_response()
+    }
+}
+
+/// Tags the request with an id, taken from the `x-request-id` header or generated, it's
+/// echoed in the response header and carried in the body of the error responses.
+pub async fn request_id(request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(String::from)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut response = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::to_bytes;
+
+    use super::*;
+
+    fn kube_error(code: u16, reason: &str) -> kube::Error {
+        let status = json!({
+            "kind": "Status",
+            "apiVersion": "v1",
+            "metadata": {},
+            "status": "Failure",
+            "message": format!("playbooks.amphitheatre.app \"test\" {reason}"),
+            "reason": reason,
+            "code": code,
+        });
+        kube::Error::Api(serde_json::from_value(status).unwrap())
+    }
+
+    fn resource_error(code: u16, reason: &str) -> ApiError {
+        ApiError::ResourceError(amp_resources::error::Error::KubeError(kube_error(code, reason)))
+    }
+
+    #[test]
+    fn test_parts() {
+        let table = [
+            (resource_error(404, "NotFound"), StatusCode::NOT_FOUND, "not_found"),
+            (resource_error(409, "Conflict"), StatusCode::CONFLICT, "conflict"),
+            (resource_error(422, "Invalid"), StatusCode::UNPROCESSABLE_ENTITY, "validation_failed"),
+            (resource_error(500, "InternalError"), StatusCode::INTERNAL_SERVER_ERROR, "resource_error"),
+            (ApiError::KubernetesError(kube_error(404, "NotFound")), StatusCode::NOT_FOUND, "not_found"),
+            (
+                ApiError::KubernetesError(kube_error(403, "Forbidden")),
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "kubernetes_error",
+            ),
+            (ApiError::NotFound, StatusCode::NOT_FOUND, "not_found"),
+            (ApiError::Unauthorized, StatusCode::UNAUTHORIZED, "unauthorized"),
+            (ApiError::Forbidden("owner".into()), StatusCode::FORBIDDEN, "forbidden"),
+            (ApiError::Conflict, StatusCode::CONFLICT, "conflict"),
+            (ApiError::PreconditionFailed, StatusCode::PRECONDITION_FAILED, "precondition_failed"),
+            (ApiError::ValidationError("bad".into()), StatusCode::UNPROCESSABLE_ENTITY, "validation_failed"),
+            (ApiError::InternalServerError, StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
+        ];
+
+        for (error, status, code) in table {
+            let (actual_status, actual_code, _) = error.parts();
+            assert_eq!((actual_status, actual_code), (status, code), "{error}");
+        }
+    }
+
+    #[test]
+    fn test_parts_details() {
+        let (_, _, details) = resource_error(404, "NotFound").parts();
+        assert_eq!(details.unwrap()["reason"], "NotFound");
+
+        let (_, _, details) = ApiError::Forbidden("owner".into()).parts();
+        assert_eq!(details, Some(json!({ "required_role": "owner" })));
+
+        let (_, _, details) = ApiError::ValidationError("limit must be greater than 0".into()).parts();
+        assert_eq!(details, Some(json!({ "reason": "limit must be greater than 0" })));
+
+        let (_, _, details) = ApiError::NotFound.parts();
+        assert_eq!(details, None);
+    }
+
+    #[test]
+    fn test_serialize_error_response() {
+        let body = ErrorResponse {
+            code: "not_found".into(),
+            message: "Not Found".into(),
+            details: None,
+            request_id: Some("1".into()),
+        };
+
+        assert_eq!(
+            serde_json::to_value(body).unwrap(),
+            json!({ "code": "not_found", "message": "Not Found", "details": null, "request_id": "1" })
+        );
+    }
+
+    async fn body(response: Response) -> Value {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_into_response() {
+        let response = REQUEST_ID.scope("1".into(), async { resource_error(404, "NotFound").into_response() }).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let body = body(response).await;
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["message"], "Not Found");
+        assert_eq!(body["details"]["reason"], "NotFound");
+        assert_eq!(body["request_id"], "1");
+    }
+
+    #[tokio::test]
+    async fn test_into_response_without_request_id() {
+        let response = ApiError::Conflict.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
 
-        error!("{} - {}", status, message);
-        (status, Json(json!({ "message": message }))).into_response()
+        let body = body(response).await;
+        assert_eq!(body["code"], "conflict");
+        assert_eq!(body["request_id"], Value::Null);
     }
 }
@@ -25,12 +25,15 @@ use tokio_stream::wrappers::ReceiverStream;
 use tracing::info;
 use uuid::Uuid;
 
-use amp_common::resource::ActorSpec;
 use amp_common::sync::Synchronization;
 
 use super::Result;
 use crate::context::Context;
-use crate::errors::ApiError;
+use crate::errors::{ApiError, ErrorResponse};
+use crate::requests::actor::{RollbackActorRequest, ScaleActorRequest, SyncManifestRequest};
+use crate::requests::{ListQuery, Params, Payload};
+use crate::responses::actor::{ActorResponse, RollbackResponse, SyncIgnoreRules, SyncResponse, SyncStatus};
+use crate::responses::Page;
 use crate::services::actor::ActorService;
 use crate::services::logger::Logger;
 
@@ -42,15 +45,21 @@ use crate::services::logger::Logger;
     get, path = "/v1/playbooks/{pid}/actors",
     params(
         ("pid" = Uuid, description = "The id of playbook"),
+        ListQuery,
     ),
     responses(
-        (status = 200, description="List all actors of playbook successfully", body = [ActorSpec]),
-        (status = 404, description = "Playbook not found")
+        (status = 200, description="List all actors of playbook successfully", body = Page<ActorResponse>),
+        (status = 404, description = "Playbook not found", body = ErrorResponse),
+        (status = 422, description = "The query parameters are invalid", body = ErrorResponse),
     ),
     tag = "Actors"
 )]
-pub async fn list(Path(pid): Path<Uuid>, State(ctx): State<Arc<Context>>) -> Result<impl IntoResponse> {
-    Ok(Json(ActorService::list(ctx, pid).await?))
+pub async fn list(
+    Path(pid): Path<Uuid>,
+    State(ctx): State<Arc<Context>>,
+    Params(query): Params<ListQuery>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(ActorService::list(ctx, pid, &query).await?))
 }
 
 /// Returns a actor detail.
@@ -61,8 +70,8 @@ pub async fn list(Path(pid): Path<Uuid>, State(ctx): State<Arc<Context>>) -> Res
         ("name" = String, description = "The name of actor"),
     ),
     responses(
-        (status = 200, description="Actor found successfully", body = ActorSpec),
-        (status = 404, description = "Actor not found")
+        (status = 200, description="Actor found successfully", body = ActorResponse),
+        (status = 404, description = "Actor not found", body = ErrorResponse)
     ),
     tag = "Actors"
 )]
@@ -82,7 +91,7 @@ pub async fn detail(
     ),
     responses(
         (status = 200, description="Actor's logs found successfully"),
-        (status = 404, description = "Actor not found")
+        (status = 404, description = "Actor not found", body = ErrorResponse)
     ),
     tag = "Actors"
 )]
@@ -104,6 +113,62 @@ pub async fn logs(
     Sse::new(stream).keep_alive(KeepAlive::default())
 }
 
+/// Lists the build history of actor, the newest first.
+#[utoipa::path(
+    get, path = "/v1/actors/{pid}/{name}/builds",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    responses(
+        (status = 200, description="Actor's builds found successfully"),
+        (status = 404, description = "Actor not found", body = ErrorResponse)
+    ),
+    tag = "Actors"
+)]
+pub async fn builds(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(ActorService::builds(ctx, pid, name).await?))
+}
+
+/// Output the log streams of actor's builds, including the finished ones.
+#[utoipa::path(
+    get, path = "/v1/actors/{pid}/{name}/builds/logs",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    responses(
+        (status = 200, description="Actor's build logs found successfully"),
+        (status = 404, description = "Actor not found", body = ErrorResponse)
+    ),
+    tag = "Actors"
+)]
+pub async fn build_logs(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+) -> Result<Sse<impl Stream<Item = axum::response::Result<Event, Infallible>>>> {
+    info!("Start to tail the build log stream of actor {} in {}...", name, pid);
+    let selectors = ActorService::build_selectors(ctx.clone(), pid, name).await?;
+    let (sender, receiver) = tokio::sync::mpsc::channel(100);
+
+    // Watch the build pods, output all the logs since the build may be finished.
+    for selector in selectors {
+        let client = ctx.k8s.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            Logger::with_labels(client, sender, pid, &selector, None).start().await;
+        });
+    }
+
+    let stream = ReceiverStream::new(receiver);
+    let stream = stream.map(Ok);
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
 /// Returns a actor's info, including environments, volumes...
 #[utoipa::path(
     get, path = "/v1/actors/{pid}/{name}/info",
@@ -113,7 +178,7 @@ pub async fn logs(
     ),
     responses(
         (status = 200, description="Actor's info found successfully"),
-        (status = 404, description = "Actor not found")
+        (status = 404, description = "Actor not found", body = ErrorResponse)
     ),
     tag = "Actors"
 )]
@@ -133,7 +198,7 @@ pub async fn info(
     ),
     responses(
         (status = 200, description="Actor's stats found successfully"),
-        (status = 404, description = "Actor not found")
+        (status = 404, description = "Actor not found", body = ErrorResponse)
     ),
     tag = "Actors"
 )]
@@ -157,16 +222,236 @@ pub async fn stats(
         content_type = "application/json"
     ),
     responses(
-        (status = 200, description="Sync the actor's sources successfully"),
-        (status = 404, description = "Actor not found")
+        (status = 202, description="Sync the actor's sources successfully", body = SyncResponse),
+        (status = 404, description = "Actor not found", body = ErrorResponse)
     ),
     tag = "Actors"
 )]
 pub async fn sync(
     State(ctx): State<Arc<Context>>,
     Path((pid, name)): Path<(Uuid, String)>,
-    Json(req): Json<Synchronization>,
+    Payload(req): Payload<Synchronization>,
+) -> Result<impl IntoResponse> {
+    let response = ActorService::sync(ctx, pid, name, req).await.map_err(ApiError::NatsError)?;
+    Ok((StatusCode::ACCEPTED, Json(response)))
+}
+
+/// Returns the sync status reported by the actor's syncer, the clients can wait
+/// until the sequence of their synchronization is applied.
+#[utoipa::path(
+    get, path = "/v1/actors/{pid}/{name}/sync/status",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    responses(
+        (status = 200, description = "The sync status of actor", body = SyncStatus),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
+    ),
+    tag = "Actors"
+)]
+pub async fn sync_status(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(ActorService::sync_status(ctx, pid, name).await.map_err(ApiError::NatsError)?))
+}
+
+/// Stream the changes made inside the actor's container, e.g. by code generators
+/// or formatters, each event is a file synchronization the clients can apply.
+#[utoipa::path(
+    get, path = "/v1/actors/{pid}/{name}/sync/changes",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    responses(
+        (status = 200, description = "The changes of actor's workspace", content_type = "text/event-stream"),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
+    ),
+    tag = "Actors"
+)]
+pub async fn sync_changes(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+) -> Result<Sse<impl Stream<Item = axum::response::Result<Event, Infallible>>>> {
+    info!("Start to stream the workspace changes of actor {} in {}...", name, pid);
+    let subscriber = ActorService::sync_changes(ctx, pid, name).await.map_err(ApiError::NatsError)?;
+    let stream = subscriber.map(|message| Ok(Event::default().data(String::from_utf8_lossy(&message.payload))));
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Returns the ignore rules of the actor's workspace, the clients should not upload
+/// the matched paths, they are never written or deleted by the syncer anyway.
+#[utoipa::path(
+    get, path = "/v1/actors/{pid}/{name}/sync/ignore",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    responses(
+        (status = 200, description = "The ignore rules of actor's workspace", body = SyncIgnoreRules),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
+    ),
+    tag = "Actors"
+)]
+pub async fn sync_ignore(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(ActorService::sync_ignore(ctx, pid, name).await.map_err(ApiError::NatsError)?))
+}
+
+/// Compare the manifest of the client's workspace with the actor's, and returns
+/// the paths of files which are missing or changed, only these need to be synced.
+#[utoipa::path(
+    post, path = "/v1/actors/{pid}/{name}/sync/manifest",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    request_body(
+        content = inline(SyncManifestRequest),
+        description = "The manifest of the client's workspace",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "The paths of missing or changed files", body = [String]),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
+    ),
+    tag = "Actors"
+)]
+pub async fn sync_manifest(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+    Payload(req): Payload<SyncManifestRequest>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(ActorService::manifest(ctx, pid, name, req).await.map_err(ApiError::NatsError)?))
+}
+
+/// Remove a actor from playbook.
+#[utoipa::path(
+    delete, path = "/v1/actors/{pid}/{name}",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    responses(
+        (status = 204, description = "Actor deleted successfully"),
+        (status = 404, description = "Actor not found", body = ErrorResponse),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
+    ),
+    tag = "Actors"
+)]
+pub async fn delete(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse> {
+    ActorService::delete(ctx, pid, name).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Restart a actor with a rolling update.
+#[utoipa::path(
+    post, path = "/v1/actors/{pid}/{name}/actions/restart",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    responses(
+        (status = 204, description = "Actor restarted successfully"),
+        (status = 404, description = "Actor not found", body = ErrorResponse),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
+    ),
+    tag = "Actors"
+)]
+pub async fn restart(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse> {
+    ActorService::restart(ctx, pid, name).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Rebuild a actor's image and deploy it again.
+#[utoipa::path(
+    post, path = "/v1/actors/{pid}/{name}/actions/rebuild",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    responses(
+        (status = 204, description = "Actor rebuilding started successfully"),
+        (status = 404, description = "Actor not found", body = ErrorResponse),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
+    ),
+    tag = "Actors"
+)]
+pub async fn rebuild(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse> {
+    ActorService::rebuild(ctx, pid, name).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Scale a actor to the given replicas.
+#[utoipa::path(
+    post, path = "/v1/actors/{pid}/{name}/actions/scale",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    request_body(
+        content = inline(ScaleActorRequest),
+        description = "Scale actor request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 204, description = "Actor scaled successfully"),
+        (status = 404, description = "Actor not found", body = ErrorResponse),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
+    ),
+    tag = "Actors"
+)]
+pub async fn scale(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+    Payload(req): Payload<ScaleActorRequest>,
+) -> Result<impl IntoResponse> {
+    ActorService::scale(ctx, pid, name, req.replicas).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Roll back a actor to a previously built image, without rebuilding.
+#[utoipa::path(
+    post, path = "/v1/actors/{pid}/{name}/actions/rollback",
+    params(
+        ("pid" = Uuid, description = "The id of playbook"),
+        ("name" = String, description = "The name of actor"),
+    ),
+    request_body(
+        content = inline(RollbackActorRequest),
+        description = "Rollback actor request",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Actor rolled back successfully", body = RollbackResponse),
+        (status = 404, description = "Actor not found", body = ErrorResponse),
+        (status = 422, description = "The image is not given or does not exist", body = ErrorResponse),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
+    ),
+    tag = "Actors"
+)]
+pub async fn rollback(
+    State(ctx): State<Arc<Context>>,
+    Path((pid, name)): Path<(Uuid, String)>,
+    Payload(req): Payload<RollbackActorRequest>,
 ) -> Result<impl IntoResponse> {
-    ActorService::sync(ctx, pid, name, req).await.map_err(ApiError::NatsError)?;
-    Ok(StatusCode::ACCEPTED)
+    Ok(Json(ActorService::rollback(ctx, pid, name, &req).await?))
 }
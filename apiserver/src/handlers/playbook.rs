@@ -17,22 +17,28 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::header::{ETAG, IF_MATCH};
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::sse::{Event, KeepAlive};
 use axum::response::{IntoResponse, Sse};
-use axum::Json;
+use axum::{Extension, Json};
 use futures::Stream;
 use k8s_openapi::api::core::v1::Event as KEvent;
 use kube::runtime::{watcher, WatchStreamExt};
-use kube::Api;
+use kube::{Api, ResourceExt};
 use tokio_stream::StreamExt as _;
 use uuid::Uuid;
 
-use amp_common::resource::PlaybookSpec;
+use amp_common::resource::{Playbook, PlaybookSpec};
 
 use super::Result;
+use crate::auth::User;
 use crate::context::Context;
+use crate::errors::ErrorResponse;
 use crate::requests::playbook::{CreatePlaybookRequest, UpdatePlaybookRequest};
+use crate::requests::{ListQuery, Params, Payload};
+use crate::responses::playbook::PlaybookResponse;
+use crate::responses::Page;
 use crate::services::playbook::PlaybookService;
 
 // The Playbooks Service Handlers.
@@ -41,14 +47,20 @@ use crate::services::playbook::PlaybookService;
 /// Lists the playbooks in the current account.
 #[utoipa::path(
     get, path = "/v1/playbooks",
+    params(ListQuery),
     responses(
-        (status = 200, description = "List all playbooks successfully", body = [PlaybookSpec]),
-        (status = 500, description = "Internal Server Error"),
+        (status = 200, description = "List all playbooks successfully", body = Page<PlaybookResponse>),
+        (status = 422, description = "The query parameters are invalid", body = ErrorResponse),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
     ),
     tag = "Playbooks"
 )]
-pub async fn list(State(ctx): State<Arc<Context>>) -> Result<impl IntoResponse> {
-    Ok(Json(PlaybookService::list(ctx).await?))
+pub async fn list(
+    State(ctx): State<Arc<Context>>,
+    Extension(user): Extension<User>,
+    Params(query): Params<ListQuery>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(PlaybookService::list(ctx, &user, &query).await?))
 }
 
 /// Create a playbook in the current account.
@@ -60,15 +72,17 @@ pub async fn list(State(ctx): State<Arc<Context>>) -> Result<impl IntoResponse>
         content_type = "application/json"
     ),
     responses(
-        (status = 201, description = "Playbook created successfully", body = PlaybookSpec)
+        (status = 201, description = "Playbook created successfully", body = PlaybookSpec),
+        (status = 422, description = "The request body is invalid", body = ErrorResponse),
     ),
     tag = "Playbooks"
 )]
 pub async fn create(
     State(ctx): State<Arc<Context>>,
-    Json(req): Json<CreatePlaybookRequest>,
+    Extension(user): Extension<User>,
+    Payload(req): Payload<CreatePlaybookRequest>,
 ) -> Result<impl IntoResponse> {
-    Ok((StatusCode::CREATED, Json(PlaybookService::create(ctx, &req).await?)))
+    Ok((StatusCode::CREATED, Json(PlaybookService::create(ctx, &req, &user).await?)))
 }
 
 /// Returns a playbook detail.
@@ -78,14 +92,16 @@ pub async fn create(
         ("id" = Uuid, description = "The id of playbook"),
     ),
     responses(
-        (status = 200, description = "Playbook found successfully", body = PlaybookSpec),
-        (status = 404, description = "Playbook not found"),
-        (status = 500, description = "Internal Server Error"),
+        (status = 200, description = "Playbook found successfully", body = PlaybookResponse),
+        (status = 404, description = "Playbook not found", body = ErrorResponse),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
     ),
     tag = "Playbooks"
 )]
 pub async fn detail(Path(id): Path<Uuid>, State(ctx): State<Arc<Context>>) -> Result<impl IntoResponse> {
-    Ok(Json(PlaybookService::get(ctx, id).await?))
+    let playbook = PlaybookService::get(ctx, id).await?;
+
+    Ok(([(ETAG, etag(&playbook))], Json(PlaybookResponse::from(playbook))))
 }
 
 /// Update a playbook.
@@ -93,6 +109,7 @@ pub async fn detail(Path(id): Path<Uuid>, State(ctx): State<Arc<Context>>) -> Re
     patch, path = "/v1/playbooks/{id}",
     params(
         ("id" = Uuid, description = "The id of playbook"),
+        ("If-Match" = Option<String>, Header, description = "The expected resource version (ETag) of playbook"),
     ),
     request_body(
         content = inline(UpdatePlaybookRequest),
@@ -100,17 +117,23 @@ pub async fn detail(Path(id): Path<Uuid>, State(ctx): State<Arc<Context>>) -> Re
         content_type = "application/json"
     ),
     responses(
-        (status = 200, description = "Playbook updated successfully", body = PlaybookSpec),
-        (status = 404, description = "Playbook not found")
+        (status = 200, description = "Playbook updated successfully", body = PlaybookResponse),
+        (status = 404, description = "Playbook not found", body = ErrorResponse),
+        (status = 409, description = "Playbook was changed by others while updating", body = ErrorResponse),
+        (status = 412, description = "Playbook version does not match the If-Match header", body = ErrorResponse),
+        (status = 422, description = "The request body is invalid", body = ErrorResponse),
     ),
     tag = "Playbooks"
 )]
 pub async fn update(
     Path(id): Path<Uuid>,
     State(ctx): State<Arc<Context>>,
-    Json(req): Json<UpdatePlaybookRequest>,
+    headers: HeaderMap,
+    Payload(req): Payload<UpdatePlaybookRequest>,
 ) -> Result<impl IntoResponse> {
-    Ok(Json(PlaybookService::update(ctx, id, &req).await?))
+    let playbook = PlaybookService::update(ctx, id, &req, if_match(&headers)).await?;
+
+    Ok(([(ETAG, etag(&playbook))], Json(PlaybookResponse::from(playbook))))
 }
 
 /// Delete a playbook
@@ -121,7 +144,7 @@ pub async fn update(
     ),
     responses(
         (status = 204, description = "Playbook deleted successfully"),
-        (status = 404, description = "Playbook not found")
+        (status = 404, description = "Playbook not found", body = ErrorResponse)
     ),
     tag = "Playbooks"
 )]
@@ -139,7 +162,7 @@ pub async fn delete(Path(id): Path<Uuid>, State(ctx): State<Arc<Context>>) -> Re
     ),
     responses(
         (status = 200, description="Playbook's events found successfully"),
-        (status = 404, description = "Playbook not found")
+        (status = 404, description = "Playbook not found", body = ErrorResponse)
     ),
     tag = "Playbooks"
 )]
@@ -169,13 +192,17 @@ pub async fn events(
     ),
     responses(
         (status = 204, description = "Playbook started successfully"),
-        (status = 404, description = "Playbook not found"),
-        (status = 500, description = "Internal Server Error"),
+        (status = 404, description = "Playbook not found", body = ErrorResponse),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
     ),
     tag = "Playbooks"
 )]
-pub async fn start(Path(id): Path<Uuid>, State(ctx): State<Arc<Context>>) -> Result<impl IntoResponse> {
-    PlaybookService::start(ctx, id).await?;
+pub async fn start(
+    Path(id): Path<Uuid>,
+    State(ctx): State<Arc<Context>>,
+    Extension(user): Extension<User>,
+) -> Result<impl IntoResponse> {
+    PlaybookService::start(ctx, id, &user.name).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
@@ -188,13 +215,33 @@ pub async fn start(Path(id): Path<Uuid>, State(ctx): State<Arc<Context>>) -> Res
     ),
     responses(
         (status = 204, description = "Playbook stopped successfully"),
-        (status = 404, description = "Playbook not found"),
-        (status = 500, description = "Internal Server Error"),
+        (status = 404, description = "Playbook not found", body = ErrorResponse),
+        (status = 500, description = "Internal Server Error", body = ErrorResponse),
     ),
     tag = "Playbooks",
 )]
-pub async fn stop(Path(id): Path<Uuid>, State(ctx): State<Arc<Context>>) -> Result<impl IntoResponse> {
-    PlaybookService::stop(ctx, id).await?;
+pub async fn stop(
+    Path(id): Path<Uuid>,
+    State(ctx): State<Arc<Context>>,
+    Extension(user): Extension<User>,
+) -> Result<impl IntoResponse> {
+    PlaybookService::stop(ctx, id, &user.name).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Returns the resource version of playbook as an entity tag.
+fn etag(playbook: &Playbook) -> String {
+    format!("\"{}\"", playbook.resource_version().unwrap_or_default())
+}
+
+/// Returns the resource version expected by the client from the `If-Match` header,
+/// the wildcard `*` matches any version.
+fn if_match(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(IF_MATCH)?.to_str().ok()?.trim();
+    if value == "*" {
+        return None;
+    }
+
+    Some(value.trim_start_matches("W/").trim_matches('"').to_string())
+}
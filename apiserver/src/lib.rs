@@ -13,11 +13,13 @@
 // limitations under the License.
 
 pub mod app;
+pub mod auth;
 pub mod config;
 pub mod context;
 pub mod errors;
 pub mod handlers;
 pub mod requests;
+pub mod responses;
 pub mod routes;
 pub mod services;
 pub mod swagger;
@@ -0,0 +1,47 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ScaleActorRequest {
+    /// The desired number of replicas, zero scales the actor down without deleting it.
+    #[schema(minimum = 0)]
+    pub replicas: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RollbackActorRequest {
+    /// The tag of the previously built image, e.g. the revision it's built from.
+    pub tag: Option<String>,
+    /// The digest of the previously built image, e.g. `sha256:...`.
+    pub digest: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncManifestRequest {
+    /// The files of the client's workspace.
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ManifestEntry {
+    /// The path relative to the workspace.
+    pub path: String,
+    /// The size of file in bytes.
+    pub size: u64,
+    /// The lowercase hex encoded SHA-256 of file content.
+    pub hash: String,
+}
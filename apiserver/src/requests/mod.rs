@@ -12,4 +12,84 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod actor;
 pub mod playbook;
+
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{FromRequest, FromRequestParts, Query, Request};
+use axum::http::request::Parts;
+use axum::Json;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::errors::ApiError;
+
+/// The query parameters of the list endpoints.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// The maximum number of items to return.
+    pub limit: Option<u32>,
+    /// The cursor returned as `next` by the previous page, it's a Kubernetes continuation token,
+    /// so it can't be used with `sort`.
+    #[serde(rename = "continue")]
+    pub cursor: Option<String>,
+    /// The Kubernetes label selector, e.g. `app=web,tier!=cache`.
+    pub labels: Option<String>,
+    /// Only the items in the given state, e.g. `running`, `failed`.
+    pub state: Option<String>,
+    /// The order of the items, by name if it's not given. The sorted list is not paginated:
+    /// `limit` returns the first items only, `next` is always None and `continue` is rejected.
+    pub sort: Option<Sort>,
+}
+
+/// The order of the listed items, by the creation time or the title, `-` for descending.
+#[derive(Clone, Copy, Debug, Deserialize, ToSchema)]
+pub enum Sort {
+    #[serde(rename = "created")]
+    Created,
+    #[serde(rename = "-created")]
+    CreatedDesc,
+    #[serde(rename = "title")]
+    Title,
+    #[serde(rename = "-title")]
+    TitleDesc,
+}
+
+/// Extracts a JSON request body like `axum::Json`, but rejects a malformed one with
+/// a `validation_failed` error so that it's reported in the same shape as the others.
+pub struct Payload<T>(pub T);
+
+impl<T, S> FromRequest<S> for Payload<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(Payload(value)),
+            Err(rejection) => Err(ApiError::ValidationError(rejection.body_text())),
+        }
+    }
+}
+
+/// Extracts the query parameters like `axum::extract::Query`, but rejects the malformed ones
+/// with a `validation_failed` error.
+pub struct Params<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for Params<T>
+where
+    Query<T>: FromRequestParts<S, Rejection = QueryRejection>,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(Params(value)),
+            Err(rejection) => Err(ApiError::ValidationError(rejection.body_text())),
+        }
+    }
+}
@@ -12,10 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use amp_common::resource::Preface;
+use std::collections::BTreeMap;
+
+use amp_common::resource::{CharacterSpec, Preface};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::auth::Role;
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct CreatePlaybookRequest {
     pub title: String,
@@ -27,4 +31,20 @@ pub struct CreatePlaybookRequest {
 pub struct UpdatePlaybookRequest {
     pub title: Option<String>,
     pub description: Option<String>,
+    /// Change the preface, the playbook will be resolved again.
+    pub preface: Option<Preface>,
+    /// Add characters, or replace the existing characters with the same name.
+    pub characters: Option<Vec<CharacterSpec>>,
+    /// Remove the characters (and their actors) by name.
+    pub remove_characters: Option<Vec<String>>,
+    /// Grant the roles to the users, replacing all the existing grants, the owner is not changed.
+    pub roles: Option<BTreeMap<String, Role>>,
+}
+
+impl UpdatePlaybookRequest {
+    /// Check if the request changes the structure of the playbook,
+    /// which means the playbook needs to be resolved again.
+    pub fn is_structural(&self) -> bool {
+        self.preface.is_some() || self.characters.is_some() || self.remove_characters.is_some()
+    }
 }
@@ -0,0 +1,131 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_common::resource::{Actor, ActorSpec};
+use amp_resources::{actor, sync};
+use kube::Resource;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{ResourceMeta, StatusSummary};
+
+/// An actor with its metadata and the summarized status.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ActorResponse {
+    pub metadata: ResourceMeta,
+    pub spec: ActorSpec,
+    pub status: StatusSummary,
+    /// The image deployed, it's pinned by the digest of the last build if known,
+    /// or differs from the one of spec if the actor is rolled back.
+    pub image: String,
+}
+
+impl From<Actor> for ActorResponse {
+    fn from(actor: Actor) -> Self {
+        let conditions = actor.status.as_ref().map(|status| status.conditions.as_slice()).unwrap_or_default();
+        Self {
+            metadata: ResourceMeta::from(actor.meta()),
+            status: StatusSummary::from(conditions),
+            image: actor::deployed_image(&actor).to_string(),
+            spec: actor.spec,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RollbackResponse {
+    /// The image the actor is rolled back to, it's deployed until the next successful build.
+    pub image: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncResponse {
+    /// The stream sequence of the synchronization, the last one if it's chunked.
+    /// The change is live once the `last_applied_sequence` of sync status reaches it.
+    pub sequence: u64,
+}
+
+/// The result of handling a synchronization message by the syncer.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "outcome", rename_all = "lowercase")]
+pub enum SyncOutcome {
+    Applied,
+    Failed {
+        reason: String,
+    },
+    Skipped {
+        reason: String,
+    },
+    /// Failed too many times and moved to the dead-letter subject of playbook.
+    #[serde(rename = "dead-lettered")]
+    DeadLettered {
+        reason: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncResult {
+    /// The stream sequence of the synchronization.
+    pub sequence: u64,
+    /// The kind of synchronization event, e.g. `Modify`, unknown if the message is invalid.
+    pub kind: Option<String>,
+    #[serde(flatten)]
+    pub outcome: SyncOutcome,
+    /// The paths touched by the applied synchronization, None if they are unknown or too many.
+    pub paths: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct SyncStatus {
+    /// The sequence of the last handled synchronization, whatever the outcome is.
+    pub last_sequence: Option<u64>,
+    /// The sequence of the last applied synchronization.
+    pub last_applied_sequence: Option<u64>,
+    /// The recent failed or skipped synchronizations, the newest last.
+    pub errors: Vec<SyncResult>,
+}
+
+impl From<sync::Outcome> for SyncOutcome {
+    fn from(outcome: sync::Outcome) -> Self {
+        match outcome {
+            sync::Outcome::Applied => SyncOutcome::Applied,
+            sync::Outcome::Failed { reason } => SyncOutcome::Failed { reason },
+            sync::Outcome::Skipped { reason } => SyncOutcome::Skipped { reason },
+            sync::Outcome::DeadLettered { reason } => SyncOutcome::DeadLettered { reason },
+        }
+    }
+}
+
+impl From<sync::SyncResult> for SyncResult {
+    fn from(result: sync::SyncResult) -> Self {
+        Self { sequence: result.sequence, kind: result.kind, outcome: result.outcome.into(), paths: result.paths }
+    }
+}
+
+impl From<sync::Status> for SyncStatus {
+    fn from(status: sync::Status) -> Self {
+        Self {
+            last_sequence: status.last_sequence,
+            last_applied_sequence: status.last_applied_sequence,
+            errors: status.errors.into_iter().map(SyncResult::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct SyncIgnoreRules {
+    /// The gitignore style patterns from `.gitignore` and `.ampignore` in the workspace root,
+    /// the matched paths are never written or deleted by the syncer.
+    pub patterns: Vec<String>,
+}
@@ -0,0 +1,113 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pub mod actor;
+pub mod playbook;
+
+use std::collections::BTreeMap;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, ObjectMeta};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// A page of the list endpoints.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The total number of the matched items, None if it's unknown before listing all the pages,
+    /// e.g. some items of the page are filtered out by the state or the roles.
+    pub total: Option<u64>,
+    /// The cursor of the next page, passed as the `continue` parameter, None for the last page
+    /// or the sorted list.
+    pub next: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page { items: self.items.into_iter().map(f).collect(), total: self.total, next: self.next }
+    }
+}
+
+/// The metadata of a Kubernetes resource.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ResourceMeta {
+    pub name: Option<String>,
+    pub uid: Option<String>,
+    /// The creation time in RFC 3339 format.
+    pub creation_timestamp: Option<String>,
+    pub labels: BTreeMap<String, String>,
+}
+
+impl From<&ObjectMeta> for ResourceMeta {
+    fn from(meta: &ObjectMeta) -> Self {
+        Self {
+            name: meta.name.clone(),
+            uid: meta.uid.clone(),
+            creation_timestamp: meta.creation_timestamp.as_ref().map(|time| time.0.to_string()),
+            labels: meta.labels.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// The status summarized from the conditions maintained by the controllers.
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct StatusSummary {
+    /// The current phase, e.g. `Building`, `Running` or `Failed`, None if it's not reconciled yet.
+    pub phase: Option<String>,
+    /// The reason of the current phase, e.g. `BuildFailed`.
+    pub reason: Option<String>,
+    /// The human readable message of the current phase.
+    pub message: Option<String>,
+    /// When the resource entered the current phase, in RFC 3339 format.
+    pub last_transition_time: Option<String>,
+    pub conditions: Vec<ConditionSummary>,
+}
+
+impl From<&[Condition]> for StatusSummary {
+    fn from(conditions: &[Condition]) -> Self {
+        let phase = amp_resources::phase(conditions);
+        Self {
+            phase: phase.map(|c| c.type_.clone()),
+            reason: phase.map(|c| c.reason.clone()),
+            message: phase.map(|c| c.message.clone()).filter(|message| !message.is_empty()),
+            last_transition_time: phase.map(|c| c.last_transition_time.0.to_string()),
+            conditions: conditions.iter().map(ConditionSummary::from).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConditionSummary {
+    /// The type of the condition, e.g. `Running`, `OutOfSync`.
+    #[serde(rename = "type")]
+    pub type_: String,
+    /// Whether the condition is true.
+    pub status: bool,
+    pub reason: String,
+    pub message: String,
+    /// When the condition last changed, in RFC 3339 format.
+    pub last_transition_time: String,
+}
+
+impl From<&Condition> for ConditionSummary {
+    fn from(condition: &Condition) -> Self {
+        Self {
+            type_: condition.type_.clone(),
+            status: condition.status == "True",
+            reason: condition.reason.clone(),
+            message: condition.message.clone(),
+            last_transition_time: condition.last_transition_time.0.to_string(),
+        }
+    }
+}
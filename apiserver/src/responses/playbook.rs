@@ -0,0 +1,39 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_common::resource::{Playbook, PlaybookSpec};
+use kube::Resource;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{ResourceMeta, StatusSummary};
+
+/// A playbook with its metadata and the summarized status.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlaybookResponse {
+    pub metadata: ResourceMeta,
+    pub spec: PlaybookSpec,
+    pub status: StatusSummary,
+}
+
+impl From<Playbook> for PlaybookResponse {
+    fn from(playbook: Playbook) -> Self {
+        let conditions = playbook.status.as_ref().map(|status| status.conditions.as_slice()).unwrap_or_default();
+        Self {
+            metadata: ResourceMeta::from(playbook.meta()),
+            status: StatusSummary::from(conditions),
+            spec: playbook.spec,
+        }
+    }
+}
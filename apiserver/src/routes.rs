@@ -24,10 +24,21 @@ pub fn build() -> Router<Arc<Context>> {
     Router::new()
         // actors
         .route("/v1/actors/{pid}/{name}", get(handlers::actor::detail))
+        .route("/v1/actors/{pid}/{name}", delete(handlers::actor::delete))
         .route("/v1/actors/{pid}/{name}/logs", get(handlers::actor::logs))
+        .route("/v1/actors/{pid}/{name}/builds", get(handlers::actor::builds))
+        .route("/v1/actors/{pid}/{name}/builds/logs", get(handlers::actor::build_logs))
         .route("/v1/actors/{pid}/{name}/info", get(handlers::actor::info))
         .route("/v1/actors/{pid}/{name}/stats", get(handlers::actor::stats))
         .route("/v1/actors/{pid}/{name}/sync", post(handlers::actor::sync))
+        .route("/v1/actors/{pid}/{name}/sync/changes", get(handlers::actor::sync_changes))
+        .route("/v1/actors/{pid}/{name}/sync/ignore", get(handlers::actor::sync_ignore))
+        .route("/v1/actors/{pid}/{name}/sync/manifest", post(handlers::actor::sync_manifest))
+        .route("/v1/actors/{pid}/{name}/sync/status", get(handlers::actor::sync_status))
+        .route("/v1/actors/{pid}/{name}/actions/restart", post(handlers::actor::restart))
+        .route("/v1/actors/{pid}/{name}/actions/rebuild", post(handlers::actor::rebuild))
+        .route("/v1/actors/{pid}/{name}/actions/scale", post(handlers::actor::scale))
+        .route("/v1/actors/{pid}/{name}/actions/rollback", post(handlers::actor::rollback))
         //
         // playbooks
         .route("/v1/playbooks", get(handlers::playbook::list))
@@ -14,30 +14,59 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
-use amp_common::resource::ActorSpec;
+use amp_common::docker::{self, registry, DockerConfig};
+use amp_common::resource::{Actor, ActorState};
+use amp_common::schema::BuildMethod;
 use amp_common::sync::Synchronization;
 use async_nats::jetstream::{self, stream};
-use tracing::error;
+use async_nats::{HeaderMap, Request, Subscriber};
+use kube::ResourceExt;
+use tracing::{error, info};
 use uuid::Uuid;
 
 use crate::context::Context;
 use crate::errors::ApiError;
-use crate::services::Result;
-use amp_resources::actor;
+use crate::requests::actor::{ManifestEntry, RollbackActorRequest, SyncManifestRequest};
+use crate::requests::ListQuery;
+use crate::responses::actor::{ActorResponse, RollbackResponse, SyncIgnoreRules, SyncResponse, SyncStatus};
+use crate::responses::Page;
+use crate::services::{paginate, Result};
+use amp_resources::build::{self, BuildRecordSpec};
+use amp_resources::kpack::image;
+use amp_resources::sync::{self, CHUNK_ID, CHUNK_INDEX, CHUNK_TOTAL};
+use amp_resources::{actor, credential, deployment, job, playbook};
+
+/// Hashing a large workspace in the syncer may take a while.
+const MANIFEST_TIMEOUT: Duration = Duration::from_secs(60);
 
 pub struct ActorService;
 
 impl ActorService {
-    pub async fn get(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<ActorSpec> {
+    pub async fn get(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<ActorResponse> {
         let actor = actor::get(&ctx.k8s, &format!("amp-{pid}"), &name).await.map_err(ApiError::ResourceError)?;
 
-        Ok(actor.spec)
+        Ok(ActorResponse::from(actor))
     }
 
-    pub async fn list(ctx: Arc<Context>, pid: Uuid) -> Result<Vec<ActorSpec>> {
-        let actors = actor::list(&ctx.k8s, &format!("amp-{pid}")).await.map_err(ApiError::ResourceError)?;
-        Ok(actors.iter().map(|actor| actor.spec.clone()).collect())
+    pub async fn list(ctx: Arc<Context>, pid: Uuid, query: &ListQuery) -> Result<Page<ActorResponse>> {
+        let namespace = format!("amp-{pid}");
+        let (k8s, namespace) = (&ctx.k8s, namespace.as_str());
+        let visible = |actor: &Actor| {
+            let conditions = actor.status.as_ref().map(|status| status.conditions.as_slice()).unwrap_or_default();
+            query.state.as_deref().is_none_or(|state| amp_resources::in_state(conditions, state))
+        };
+
+        let page = paginate(
+            query,
+            |params| async move { actor::list(k8s, namespace, &params).await },
+            visible,
+            |actor| actor.spec.name.as_str(),
+        )
+        .await?;
+
+        Ok(page.map(ActorResponse::from))
     }
 
     pub async fn sync(
@@ -45,9 +74,10 @@ impl ActorService {
         pid: Uuid,
         name: String,
         req: Synchronization,
-    ) -> Result<(), async_nats::Error> {
+    ) -> Result<SyncResponse, async_nats::Error> {
         // Connect to NATS server and create a JetStream instance
         let client = async_nats::connect(&ctx.config.nats_url).await?;
+        let limit = sync::chunk_size(client.server_info().max_payload);
         let jetstream = jetstream::new(client);
 
         // Must create a stream before publishing, otherwise the publish will fail.
@@ -62,9 +92,80 @@ impl ActorService {
         // Publish a message to the stream
         let subject = format!("{pid}.{name}");
         let payload = serde_json::to_vec(&req)?;
-        jetstream.publish(subject, payload.into()).await?.await?;
+        if payload.len() <= limit {
+            let ack = jetstream.publish(subject, payload.into()).await?.await?;
+            return Ok(SyncResponse { sequence: ack.sequence });
+        }
 
-        Ok(())
+        // Split the message into chunks to stay under the NATS max payload,
+        // the syncer reassembles them by the chunk headers.
+        let id = Uuid::new_v4().to_string();
+        let chunks: Vec<&[u8]> = payload.chunks(limit).collect();
+        let mut sequence = 0;
+        for (index, chunk) in chunks.iter().enumerate() {
+            let mut headers = HeaderMap::new();
+            headers.insert(CHUNK_ID, id.as_str());
+            headers.insert(CHUNK_INDEX, index.to_string().as_str());
+            headers.insert(CHUNK_TOTAL, chunks.len().to_string().as_str());
+
+            let ack = jetstream.publish_with_headers(subject.clone(), headers, chunk.to_vec().into()).await?.await?;
+            sequence = ack.sequence;
+        }
+        info!("Published the synchronization of {} in {} chunks", subject, chunks.len());
+
+        Ok(SyncResponse { sequence })
+    }
+
+    /// Returns the sync status reported by the syncer, it's empty if nothing synced yet.
+    pub async fn sync_status(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<SyncStatus, async_nats::Error> {
+        let client = async_nats::connect(&ctx.config.nats_url).await?;
+        let jetstream = jetstream::new(client);
+
+        // The bucket is created by the syncer when it starts.
+        let Ok(store) = jetstream.get_key_value(sync::STATUS_BUCKET).await else {
+            return Ok(SyncStatus::default());
+        };
+
+        match store.get(format!("{pid}.{name}")).await? {
+            Some(value) => Ok(SyncStatus::from(serde_json::from_slice::<sync::Status>(&value)?)),
+            None => Ok(SyncStatus::default()),
+        }
+    }
+
+    /// Returns the ignore rules of the actor's workspace, served by its syncer.
+    pub async fn sync_ignore(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<SyncIgnoreRules, async_nats::Error> {
+        let client = async_nats::connect(&ctx.config.nats_url).await?;
+        let response = client.request(format!("{pid}.{name}.ignore"), "".into()).await?;
+
+        Ok(serde_json::from_slice(&response.payload)?)
+    }
+
+    /// Subscribe the changes made inside the actor's container, published by its syncer.
+    pub async fn sync_changes(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<Subscriber, async_nats::Error> {
+        let client = async_nats::connect(&ctx.config.nats_url).await?;
+        Ok(client.subscribe(format!("{pid}.{name}.out")).await?)
+    }
+
+    /// Send the manifest of the client's workspace to the syncer, and returns the paths
+    /// it is missing, so that only these files need to be synced.
+    pub async fn manifest(
+        ctx: Arc<Context>,
+        pid: Uuid,
+        name: String,
+        req: SyncManifestRequest,
+    ) -> Result<Vec<String>, async_nats::Error> {
+        let client = async_nats::connect(&ctx.config.nats_url).await?;
+        let limit = sync::chunk_size(client.server_info().max_payload);
+        let subject = format!("{pid}.{name}.manifest");
+
+        let mut missing = vec![];
+        for batch in batches(&req.entries, limit)? {
+            let request = Request::new().payload(batch.into()).timeout(Some(MANIFEST_TIMEOUT));
+            let response = client.send_request(subject.clone(), request).await?;
+            missing.extend(serde_json::from_slice::<Vec<String>>(&response.payload)?);
+        }
+
+        Ok(missing)
     }
 
     pub async fn stats(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<HashMap<String, String>> {
@@ -95,4 +196,168 @@ impl ActorService {
 
         Ok(info)
     }
+
+    /// Lists the build history of the actor, the newest first.
+    pub async fn builds(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<Vec<BuildRecordSpec>> {
+        let namespace = format!("amp-{pid}");
+        let actor = actor::get(&ctx.k8s, &namespace, &name).await.map_err(ApiError::ResourceError)?;
+
+        let records = build::list(&ctx.k8s, &namespace, &actor.spec.name).await.map_err(ApiError::ResourceError)?;
+        Ok(records.into_iter().map(|record| record.spec).collect())
+    }
+
+    /// Returns the label selectors of the actor's build pods, which are created
+    /// by the build Job (Kaniko or lifecycle), or by the builds of kpack Image.
+    pub async fn build_selectors(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<Vec<String>> {
+        let actor = actor::get(&ctx.k8s, &format!("amp-{pid}"), &name).await.map_err(ApiError::ResourceError)?;
+        let builder = format!("{}-builder", actor.spec.name);
+
+        let mut selectors = vec![format!("job-name={builder}")];
+        let build = actor.spec.character.build.clone().unwrap_or_default();
+        if matches!(build.method(), BuildMethod::Buildpacks) {
+            selectors.push(format!("image.kpack.io/image={builder}"));
+        }
+
+        Ok(selectors)
+    }
+
+    /// Restart the actor with a rolling update of its Deployment.
+    pub async fn restart(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<()> {
+        let namespace = format!("amp-{pid}");
+        if !deployment::exists(&ctx.k8s, &namespace, &name).await.map_err(ApiError::ResourceError)? {
+            return Err(ApiError::NotFound);
+        }
+
+        deployment::restart(&ctx.k8s, &namespace, &name).await.map_err(ApiError::ResourceError)
+    }
+
+    /// Rebuild the actor's image, even if the image already exists.
+    pub async fn rebuild(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<()> {
+        let actor = actor::get(&ctx.k8s, &format!("amp-{pid}"), &name).await.map_err(ApiError::ResourceError)?;
+
+        // Delete the previous build, otherwise the builder will treat it as completed.
+        // The buildpacks image may be built by either kpack or a lifecycle Job.
+        let build = actor.spec.character.build.clone().unwrap_or_default();
+        job::delete(&ctx.k8s, &actor).await.map_err(ApiError::ResourceError)?;
+        if matches!(build.method(), BuildMethod::Buildpacks) {
+            image::delete(&ctx.k8s, &actor).await.map_err(ApiError::ResourceError)?;
+        }
+        // The deleted build never finishes, don't let the next build inherit its record.
+        build::cancel(&ctx.k8s, &actor, "Cancelled by a rebuild").await.map_err(ApiError::ResourceError)?;
+
+        // Skip the initial state, it will not build if the image already exists. The rebuilt image
+        // of the same tag is rolled out by its digest, or by restarting the Deployment, see `BuildTask`.
+        actor::patch_status(&ctx.k8s, &actor, ActorState::building()).await.map_err(ApiError::ResourceError)?;
+        info!("Rebuilding the actor {} in playbook {}", name, pid);
+
+        Ok(())
+    }
+
+    /// Scale the replicas of the actor's Deployment.
+    pub async fn scale(ctx: Arc<Context>, pid: Uuid, name: String, replicas: u16) -> Result<()> {
+        let namespace = format!("amp-{pid}");
+        if !deployment::exists(&ctx.k8s, &namespace, &name).await.map_err(ApiError::ResourceError)? {
+            return Err(ApiError::NotFound);
+        }
+
+        deployment::scale(&ctx.k8s, &namespace, &name, replicas.into()).await.map_err(ApiError::ResourceError)
+    }
+
+    /// Roll back the actor's Deployment to a previously built image, without rebuilding.
+    pub async fn rollback(
+        ctx: Arc<Context>,
+        pid: Uuid,
+        name: String,
+        req: &RollbackActorRequest,
+    ) -> Result<RollbackResponse> {
+        let namespace = format!("amp-{pid}");
+        let actor = actor::get(&ctx.k8s, &namespace, &name).await.map_err(ApiError::ResourceError)?;
+        if !deployment::exists(&ctx.k8s, &namespace, &name).await.map_err(ApiError::ResourceError)? {
+            return Err(ApiError::NotFound);
+        }
+
+        let image = match (&req.tag, &req.digest) {
+            (Some(tag), None) => format!("{}:{}", repository(&actor.spec.image), tag),
+            (None, Some(digest)) => format!("{}@{}", repository(&actor.spec.image), digest),
+            _ => return Err(ApiError::ValidationError("either tag or digest is required".into())),
+        };
+
+        // Check if the image still exists in the registry before deploying it.
+        let credentials = credential::load(&ctx.k8s, &ctx.config.namespace).await.map_err(ApiError::ResourceError)?;
+        let config = DockerConfig::from(&credentials.unwrap_or_default().registries);
+        let credential = docker::get_credential(&config, &image).ok();
+        let exists = registry::exists(&image, credential).await.map_err(|err| {
+            error!("Failed to check the image {}: {}", image, err);
+            ApiError::InternalServerError
+        })?;
+        if !exists {
+            return Err(ApiError::ValidationError(format!("the image {image} does not exist")));
+        }
+
+        // The controller deploys it instead of the built image, until the next successful build.
+        actor::annotate(&ctx.k8s, &actor, actor::ROLLBACK_IMAGE_ANNOTATION, &image)
+            .await
+            .map_err(ApiError::ResourceError)?;
+        // A failed actor is not deployed any more, run it again with the rolled back image.
+        if actor::is_failed(&actor) {
+            let condition = ActorState::running(true, "RolledBack", None);
+            actor::patch_status(&ctx.k8s, &actor, condition).await.map_err(ApiError::ResourceError)?;
+        }
+        info!("Rolled back the actor {} in playbook {} to {}", name, pid, image);
+
+        Ok(RollbackResponse { image })
+    }
+
+    /// Remove the actor from the playbook, its character will not be run again.
+    pub async fn delete(ctx: Arc<Context>, pid: Uuid, name: String) -> Result<()> {
+        let namespace = format!("amp-{pid}");
+        let actor = actor::get(&ctx.k8s, &namespace, &name).await.map_err(ApiError::ResourceError)?;
+
+        let current = playbook::get(&ctx.k8s, &pid.to_string()).await.map_err(ApiError::ResourceError)?;
+        let mut spec = current.spec.clone();
+        if let Some(characters) = spec.characters.as_mut() {
+            characters.retain(|character| character.meta.name != name);
+        }
+        playbook::update(&ctx.k8s, &pid.to_string(), &spec, current.resource_version())
+            .await
+            .map_err(ApiError::ResourceError)?;
+
+        actor::delete(&ctx.k8s, &namespace, &actor.name_any()).await.map_err(ApiError::ResourceError)?;
+        info!("Deleted the actor {} from playbook {}", name, pid);
+
+        Ok(())
+    }
+}
+
+/// Returns the repository of the image, without the tag or digest.
+fn repository(image: &str) -> &str {
+    let image = image.split_once('@').map_or(image, |(repository, _)| repository);
+    match image.rfind(':') {
+        // The colon may be the port of the registry, e.g. `localhost:5000/app`.
+        Some(index) if !image[index..].contains('/') => &image[..index],
+        _ => image,
+    }
+}
+
+/// Split the manifest entries into JSON arrays which stay under the limit.
+fn batches(entries: &[ManifestEntry], limit: usize) -> serde_json::Result<Vec<Vec<u8>>> {
+    let mut batches = vec![];
+    let mut batch: Vec<&ManifestEntry> = vec![];
+    let mut size = 2; // The brackets of array
+
+    for entry in entries {
+        let len = serde_json::to_vec(entry)?.len() + 1;
+        if !batch.is_empty() && size + len > limit {
+            batches.push(serde_json::to_vec(&batch)?);
+            batch.clear();
+            size = 2;
+        }
+        batch.push(entry);
+        size += len;
+    }
+    if !batch.is_empty() || batches.is_empty() {
+        batches.push(serde_json::to_vec(&batch)?);
+    }
+
+    Ok(batches)
 }
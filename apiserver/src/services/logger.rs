@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use axum::response::sse::Event;
 use futures::AsyncBufReadExt;
@@ -35,16 +35,30 @@ pub struct Logger {
     sender: Sender<Event>,                    // The sender of the log stream.
     config: Config,                           // The configuration of watcher.
     watches: HashMap<String, JoinHandle<()>>, // The map of watching containers.
+    finished: HashSet<String>,                // The set of terminated containers.
+    tail_lines: Option<i64>,                  // The lines of existing logs to output, None for all.
 }
 
 impl Logger {
     /// Creates a new logger.
     pub fn new(client: kube::Client, sender: Sender<Event>, playbook: Uuid, actor: String) -> Self {
-        let api: Api<Pod> = Api::namespaced(client, &format!("amp-{playbook}"));
         let label_selector = format!("amphitheatre.app/character={actor}");
-        let config = Config::default().labels(&label_selector);
+        Self::with_labels(client, sender, playbook, &label_selector, Some(100))
+    }
 
-        Self { api, sender, config, watches: HashMap::new() }
+    /// Creates a new logger for the pods matched by the label selector,
+    /// the `tail_lines` limits the existing logs of each container, None for all.
+    pub fn with_labels(
+        client: kube::Client,
+        sender: Sender<Event>,
+        playbook: Uuid,
+        label_selector: &str,
+        tail_lines: Option<i64>,
+    ) -> Self {
+        let api: Api<Pod> = Api::namespaced(client, &format!("amp-{playbook}"));
+        let config = Config::default().labels(label_selector);
+
+        Self { api, sender, config, watches: HashMap::new(), finished: HashSet::new(), tail_lines }
     }
 
     /// Starts the logger.
@@ -82,21 +96,26 @@ impl Logger {
                 continue;
             }
             let state = container.state.unwrap();
+            let key = format!("{}-{}", pod, container.name);
 
             // If the container is running, then subscribe the log stream.
             if state.running.is_some_and(|s| s.started_at.is_some()) {
-                let key = &format!("{}-{}", pod, container.name);
-                if self.watches.contains_key(key) {
+                if self.watches.contains_key(&key) {
                     debug!("Skip container {} of {} because it's watching.", &container.name, pod);
                     continue;
                 }
                 self.subscribe(pod, &container.name).await;
             }
 
-            // If the container is terminated, then unsubscribe the log stream.
+            // If the container is terminated, the log stream ends by itself, just release it.
+            // The containers terminated before watching are subscribed once for their logs.
             if state.terminated.is_some_and(|s| s.finished_at.is_some()) {
                 debug!("Container {} in {} has been terminated.", container.name, pod);
-                self.unsubscribe(pod, &container.name);
+                if !self.watches.contains_key(&key) && !self.finished.contains(&key) {
+                    self.subscribe(pod, &container.name).await;
+                }
+                self.release(pod, &container.name);
+                self.finished.insert(key);
             }
         }
     }
@@ -109,20 +128,21 @@ impl Logger {
         let sender = self.sender.clone();
         let container = container.to_string();
         let pod = pod.to_string();
+        let tail_lines = self.tail_lines;
 
         let task = tokio::spawn(async move {
-            Self::tail(api, sender, pod, container).await;
+            Self::tail(api, sender, pod, container, tail_lines).await;
         });
 
         self.watches.insert(key, task);
     }
 
     /// Tails the log stream of the container.
-    async fn tail(api: Api<Pod>, sender: Sender<Event>, pod: String, container: String) {
+    async fn tail(api: Api<Pod>, sender: Sender<Event>, pod: String, container: String, tail_lines: Option<i64>) {
         let params = LogParams {
             container: Some(container.to_string()),
             follow: true,
-            tail_lines: Some(100),
+            tail_lines,
             timestamps: false,
             ..Default::default()
         };
@@ -154,12 +174,12 @@ impl Logger {
         }
     }
 
-    /// Unsubscribes the log stream of the container.
-    fn unsubscribe(&mut self, pod: &str, container: &str) {
+    /// Releases the log stream of the terminated container, without aborting it,
+    /// so the remaining logs are still sent before the stream ends.
+    fn release(&mut self, pod: &str, container: &str) {
         let key = &format!("{pod}-{container}");
-        if let Some(task) = self.watches.remove(key) {
-            info!("Unsubscribe the log stream of container {} in {}.", container, pod);
-            task.abort();
+        if self.watches.remove(key).is_some() {
+            info!("Release the log stream of container {} in {}.", container, pod);
         }
     }
 }
@@ -17,3 +17,265 @@ pub mod logger;
 pub mod playbook;
 
 pub type Result<T, E = crate::errors::ApiError> = std::result::Result<T, E>;
+
+use std::cmp::Ordering;
+use std::future::Future;
+
+use kube::api::ListParams;
+use kube::core::ObjectList;
+use kube::Resource;
+
+use crate::errors::ApiError;
+use crate::requests::{ListQuery, Sort};
+use crate::responses::Page;
+
+/// Lists a page of the resources by the Kubernetes continuation, skipping the invisible ones.
+/// More pages are fetched until the limit is reached, asking for no more than the missing
+/// items every time, so that the continuation of the last one never skips any item.
+/// When sorted, all the resources are listed and the first `limit` ones are returned without
+/// a cursor, since the continuation follows the name order of Kubernetes.
+pub(crate) async fn paginate<K, F, Fut>(
+    query: &ListQuery,
+    list: F,
+    visible: impl Fn(&K) -> bool,
+    title: impl Fn(&K) -> &str,
+) -> Result<Page<K>>
+where
+    K: Resource + Clone,
+    F: Fn(ListParams) -> Fut,
+    Fut: Future<Output = amp_resources::error::Result<ObjectList<K>>>,
+{
+    if query.limit == Some(0) {
+        return Err(ApiError::ValidationError("limit must be greater than 0".into()));
+    }
+
+    let mut params = ListParams::default();
+    if let Some(selector) = &query.labels {
+        params = params.labels(selector);
+    }
+
+    if let Some(sort) = query.sort {
+        if query.cursor.is_some() {
+            return Err(ApiError::ValidationError("continue can't be used with sort".into()));
+        }
+
+        let resources = list(params).await.map_err(ApiError::ResourceError)?;
+        let mut items: Vec<K> = resources.items.into_iter().filter(|item| visible(item)).collect();
+        items.sort_by(|a, b| compare(sort, a, b, &title));
+
+        let total = items.len() as u64;
+        if let Some(limit) = query.limit {
+            items.truncate(limit as usize);
+        }
+
+        return Ok(Page { items, total: Some(total), next: None });
+    }
+
+    let mut items = vec![];
+    let mut cursor = query.cursor.clone();
+    let mut remaining;
+    let mut skipped = false;
+    loop {
+        let mut params = params.clone();
+        if let Some(limit) = query.limit {
+            params = params.limit(limit - items.len() as u32);
+        }
+        if let Some(token) = &cursor {
+            params = params.continue_token(token);
+        }
+
+        let resources = list(params).await.map_err(ApiError::ResourceError)?;
+        cursor = resources.metadata.continue_.filter(|token| !token.is_empty());
+        remaining = resources.metadata.remaining_item_count;
+        for item in resources.items {
+            if visible(&item) {
+                items.push(item);
+            } else {
+                skipped = true;
+            }
+        }
+
+        if cursor.is_none() || query.limit.is_some_and(|limit| items.len() >= limit as usize) {
+            break;
+        }
+    }
+
+    // The total is known only if it's listed from the beginning, and either all the pages
+    // are listed, or Kubernetes counts the remaining items and none is skipped.
+    let total = match (&query.cursor, &cursor, remaining) {
+        (None, None, _) => Some(items.len() as u64),
+        (None, Some(_), Some(remaining)) if !skipped => Some(items.len() as u64 + remaining.max(0) as u64),
+        _ => None,
+    };
+
+    Ok(Page { items, total, next: cursor })
+}
+
+fn compare<K: Resource>(sort: Sort, a: &K, b: &K, title: impl Fn(&K) -> &str) -> Ordering {
+    let created = |resource: &K| resource.meta().creation_timestamp.as_ref().map(|time| time.0);
+    match sort {
+        Sort::Created => created(a).cmp(&created(b)),
+        Sort::CreatedDesc => created(b).cmp(&created(a)),
+        Sort::Title => title(a).cmp(title(b)),
+        Sort::TitleDesc => title(b).cmp(title(a)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use k8s_openapi::api::core::v1::ConfigMap;
+    use serde_json::{json, Map, Value};
+
+    use super::*;
+
+    /// A fake Kubernetes list API over the resources in the name order, the continuation
+    /// token is the index of the next resource.
+    struct Server {
+        items: Vec<ConfigMap>,
+        requests: RefCell<Vec<(Option<u32>, Option<String>)>>,
+    }
+
+    impl Server {
+        /// Each resource is named by its title, and created in the order of the given titles.
+        fn new(titles: &[&str]) -> Self {
+            let mut items: Vec<ConfigMap> = titles
+                .iter()
+                .enumerate()
+                .map(|(index, title)| {
+                    serde_json::from_value(json!({
+                        "apiVersion": "v1",
+                        "kind": "ConfigMap",
+                        "metadata": { "name": title, "creationTimestamp": format!("2024-01-01T00:00:0{index}Z") },
+                        "data": { "title": title.to_uppercase() },
+                    }))
+                    .unwrap()
+                })
+                .collect();
+            items.sort_by_key(|item| item.metadata.name.clone());
+
+            Server { items, requests: RefCell::new(vec![]) }
+        }
+
+        fn list(&self, params: &ListParams) -> ObjectList<ConfigMap> {
+            self.requests.borrow_mut().push((params.limit, params.continue_token.clone()));
+
+            let start: usize = params.continue_token.as_deref().map_or(0, |token| token.parse().unwrap());
+            let end = params.limit.map_or(self.items.len(), |limit| (start + limit as usize).min(self.items.len()));
+
+            let mut metadata = Map::new();
+            if end < self.items.len() {
+                metadata.insert("continue".into(), json!(end.to_string()));
+                metadata.insert("remainingItemCount".into(), json!(self.items.len() - end));
+            }
+            serde_json::from_value(json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMapList",
+                "metadata": Value::Object(metadata),
+                "items": self.items[start..end],
+            }))
+            .unwrap()
+        }
+
+        async fn paginate(&self, query: &ListQuery, visible: impl Fn(&ConfigMap) -> bool) -> Result<Page<String>> {
+            let page = paginate(
+                query,
+                |params| {
+                    let list = self.list(&params);
+                    async move { Ok(list) }
+                },
+                visible,
+                |item| item.data.as_ref().and_then(|data| data.get("title")).map_or("", String::as_str),
+            )
+            .await?;
+
+            Ok(page.map(|item| item.metadata.name.unwrap_or_default()))
+        }
+    }
+
+    fn query(limit: Option<u32>, cursor: Option<&str>, sort: Option<Sort>) -> ListQuery {
+        ListQuery { limit, cursor: cursor.map(String::from), sort, ..ListQuery::default() }
+    }
+
+    #[tokio::test]
+    async fn test_paginate_by_continuation() {
+        let server = Server::new(&["a", "b", "c", "d", "e"]);
+
+        let page = server.paginate(&query(Some(2), None, None), |_| true).await.unwrap();
+        assert_eq!((page.items, page.total, page.next.as_deref()), (vec!["a".into(), "b".into()], Some(5), Some("2")));
+
+        let page = server.paginate(&query(Some(2), Some("2"), None), |_| true).await.unwrap();
+        assert_eq!((page.items, page.total, page.next.as_deref()), (vec!["c".into(), "d".into()], None, Some("4")));
+
+        let page = server.paginate(&query(Some(2), Some("4"), None), |_| true).await.unwrap();
+        assert_eq!((page.items, page.total, page.next), (vec!["e".into()], None, None));
+    }
+
+    #[tokio::test]
+    async fn test_paginate_all() {
+        let server = Server::new(&["a", "b", "c"]);
+
+        let page = server.paginate(&ListQuery::default(), |_| true).await.unwrap();
+        assert_eq!((page.items.len(), page.total, page.next), (3, Some(3), None));
+        assert_eq!(*server.requests.borrow(), vec![(None, None)]);
+    }
+
+    #[tokio::test]
+    async fn test_paginate_filtered_across_pages() {
+        let server = Server::new(&["a", "b", "c", "d", "e"]);
+        let visible = |item: &ConfigMap| !matches!(item.metadata.name.as_deref(), Some("b" | "c"));
+
+        let page = server.paginate(&query(Some(2), None, None), visible).await.unwrap();
+        assert_eq!((page.items, page.total, page.next.as_deref()), (vec!["a".into(), "d".into()], None, Some("4")));
+
+        // Only the missing items are asked, so that no item is skipped by the continuation.
+        let requests = server.requests.borrow().clone();
+        assert_eq!(requests, vec![(Some(2), None), (Some(1), Some("2".into())), (Some(1), Some("3".into()))]);
+
+        let page = server.paginate(&query(Some(2), page.next.as_deref(), None), visible).await.unwrap();
+        assert_eq!((page.items, page.total, page.next), (vec!["e".into()], None, None));
+    }
+
+    #[tokio::test]
+    async fn test_paginate_filtered_from_beginning_to_end() {
+        let server = Server::new(&["a", "b", "c"]);
+
+        let page =
+            server.paginate(&query(Some(5), None, None), |item| item.metadata.name.as_deref() != Some("b")).await;
+        let page = page.unwrap();
+        assert_eq!((page.items, page.total, page.next), (vec!["a".into(), "c".into()], Some(2), None));
+    }
+
+    #[tokio::test]
+    async fn test_paginate_sorted() {
+        // Created in the order of c, a, b.
+        let server = Server::new(&["c", "a", "b"]);
+
+        let page = server.paginate(&query(Some(2), None, Some(Sort::CreatedDesc)), |_| true).await.unwrap();
+        assert_eq!((page.items, page.total, page.next), (vec!["b".into(), "a".into()], Some(3), None));
+
+        let page = server.paginate(&query(None, None, Some(Sort::Created)), |_| true).await.unwrap();
+        assert_eq!(page.items, vec!["c".to_string(), "a".into(), "b".into()]);
+
+        let page = server.paginate(&query(Some(1), None, Some(Sort::TitleDesc)), |_| true).await.unwrap();
+        assert_eq!((page.items, page.total), (vec!["c".into()], Some(3)));
+
+        // The filter is applied before slicing.
+        let visible = |item: &ConfigMap| item.metadata.name.as_deref() != Some("a");
+        let page = server.paginate(&query(Some(2), None, Some(Sort::Title)), visible).await.unwrap();
+        assert_eq!((page.items, page.total), (vec!["b".into(), "c".into()], Some(2)));
+    }
+
+    #[tokio::test]
+    async fn test_paginate_invalid_query() {
+        let server = Server::new(&["a"]);
+
+        let result = server.paginate(&query(Some(1), Some("1"), Some(Sort::Title)), |_| true).await;
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+
+        let result = server.paginate(&query(Some(0), None, None), |_| true).await;
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+        assert!(server.requests.borrow().is_empty());
+    }
+}
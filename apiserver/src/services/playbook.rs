@@ -14,36 +14,75 @@
 
 use std::sync::Arc;
 
-use amp_common::resource::{Playbook, PlaybookSpec};
+use amp_common::resource::{Playbook, PlaybookSpec, PlaybookState};
+use amp_resources::error::Error as ResourceError;
 use amp_resources::playbook;
+use kube::ResourceExt;
+use tracing::info;
 use uuid::Uuid;
 
+use crate::auth::{Role, User};
 use crate::context::Context;
 use crate::errors::ApiError;
 use crate::requests::playbook::{CreatePlaybookRequest, UpdatePlaybookRequest};
-use crate::services::Result;
+use crate::requests::ListQuery;
+use crate::responses::playbook::PlaybookResponse;
+use crate::responses::Page;
+use crate::services::{paginate, Result};
 
 pub struct PlaybookService;
 
 impl PlaybookService {
-    pub async fn get(ctx: Arc<Context>, id: Uuid) -> Result<PlaybookSpec> {
-        let playbook = playbook::get(&ctx.k8s, &id.to_string()).await.map_err(ApiError::ResourceError)?;
+    pub async fn get(ctx: Arc<Context>, id: Uuid) -> Result<Playbook> {
+        playbook::get(&ctx.k8s, &id.to_string()).await.map_err(ApiError::ResourceError)
+    }
 
-        Ok(playbook.spec)
+    /// List the playbooks which the user is granted a role on, the admin lists all the playbooks.
+    pub async fn list(ctx: Arc<Context>, user: &User, query: &ListQuery) -> Result<Page<PlaybookResponse>> {
+        let k8s = &ctx.k8s;
+        let visible = |playbook: &Playbook| {
+            let conditions = playbook.status.as_ref().map(|status| status.conditions.as_slice()).unwrap_or_default();
+            (user.admin || Role::of(playbook, &user.name).is_some())
+                && query.state.as_deref().is_none_or(|state| amp_resources::in_state(conditions, state))
+        };
+
+        let page = paginate(
+            query,
+            |params| async move { playbook::list(k8s, &params).await },
+            visible,
+            |playbook| playbook.spec.title.as_str(),
+        )
+        .await?;
+
+        Ok(page.map(PlaybookResponse::from))
     }
 
-    pub async fn list(ctx: Arc<Context>) -> Result<Vec<PlaybookSpec>> {
-        let resources = playbook::list(&ctx.k8s).await.map_err(ApiError::ResourceError)?;
+    /// Start a stopped playbook, the controller will restore the replicas of actors.
+    pub async fn start(ctx: Arc<Context>, id: Uuid, by: &str) -> Result<()> {
+        let playbook = playbook::get(&ctx.k8s, &id.to_string()).await.map_err(ApiError::ResourceError)?;
+        if !playbook::is_stopped(&playbook) {
+            info!("The playbook {} is not stopped, nothing to start", id);
+            return Ok(());
+        }
 
-        Ok(resources.iter().map(|playbook| playbook.spec.clone()).collect())
-    }
+        let condition = playbook::starting(by);
+        playbook::patch_status(&ctx.k8s, &playbook, condition).await.map_err(ApiError::ResourceError)?;
 
-    pub async fn start(_ctx: Arc<Context>, _id: Uuid) -> Result<()> {
-        unimplemented!()
+        Ok(())
     }
 
-    pub async fn stop(_ctx: Arc<Context>, _id: Uuid) -> Result<()> {
-        unimplemented!()
+    /// Stop a playbook, the controller will scale all the actors down to zero.
+    pub async fn stop(ctx: Arc<Context>, id: Uuid, by: &str) -> Result<()> {
+        let playbook = playbook::get(&ctx.k8s, &id.to_string()).await.map_err(ApiError::ResourceError)?;
+        if playbook::is_stopped(&playbook) {
+            info!("The playbook {} is already stopped", id);
+            return Ok(());
+        }
+
+        let condition = playbook::stopped(by);
+        playbook::patch_status(&ctx.k8s, &playbook, condition).await.map_err(ApiError::ResourceError)?;
+
+        Ok(())
     }
 
     pub async fn delete(ctx: Arc<Context>, id: Uuid) -> Result<()> {
@@ -52,9 +91,9 @@ impl PlaybookService {
         Ok(())
     }
 
-    pub async fn create(ctx: Arc<Context>, req: &CreatePlaybookRequest) -> Result<PlaybookSpec> {
+    pub async fn create(ctx: Arc<Context>, req: &CreatePlaybookRequest, user: &User) -> Result<PlaybookSpec> {
         let uuid = Uuid::new_v4();
-        let resource = Playbook::new(
+        let mut resource = Playbook::new(
             &uuid.to_string(),
             PlaybookSpec {
                 id: uuid.to_string(),
@@ -64,13 +103,76 @@ impl PlaybookService {
                 ..PlaybookSpec::default()
             },
         );
+        playbook::set_owner(&mut resource, &user.name);
 
         let playbook = playbook::create(&ctx.k8s, &resource).await.map_err(ApiError::ResourceError)?;
 
         Ok(playbook.spec)
     }
 
-    pub async fn update(_ctx: Arc<Context>, _id: Uuid, _req: &UpdatePlaybookRequest) -> Result<PlaybookSpec> {
-        unimplemented!()
+    /// Update a playbook, the `version` is the resource version expected by the client (`If-Match`).
+    pub async fn update(
+        ctx: Arc<Context>,
+        id: Uuid,
+        req: &UpdatePlaybookRequest,
+        version: Option<String>,
+    ) -> Result<Playbook> {
+        let current = playbook::get(&ctx.k8s, &id.to_string()).await.map_err(ApiError::ResourceError)?;
+        if version.is_some() && version != current.resource_version() {
+            return Err(ApiError::PreconditionFailed);
+        }
+
+        let mut spec = current.spec.clone();
+        if let Some(title) = &req.title {
+            spec.title.clone_from(title);
+        }
+        if let Some(description) = &req.description {
+            spec.description = Some(description.clone());
+        }
+        if let Some(preface) = &req.preface {
+            spec.preface = preface.clone();
+        }
+
+        // Remove the characters first, then add or replace the new ones.
+        if req.characters.is_some() || req.remove_characters.is_some() {
+            let mut characters = spec.characters.take().unwrap_or_default();
+            if let Some(names) = &req.remove_characters {
+                characters.retain(|character| !names.contains(&character.meta.name));
+            }
+            if let Some(items) = &req.characters {
+                for item in items {
+                    characters.retain(|character| character.meta.name != item.meta.name);
+                    characters.push(item.clone());
+                }
+            }
+            spec.characters = Some(characters);
+        }
+
+        // Always guard with the version we read, so that the concurrent edits
+        // between reading and updating are rejected with a conflict too.
+        let playbook =
+            playbook::update(&ctx.k8s, &id.to_string(), &spec, current.resource_version()).await.map_err(|err| {
+                match err {
+                    ResourceError::KubeError(kube::Error::Api(ref response)) if response.code == 409 => {
+                        ApiError::Conflict
+                    }
+                    err => ApiError::ResourceError(err),
+                }
+            })?;
+
+        if let Some(roles) = &req.roles {
+            let grants = roles.iter().map(|(user, role)| (user.clone(), role.to_string())).collect();
+            playbook::set_grants(&ctx.k8s, &id.to_string(), &grants).await.map_err(ApiError::ResourceError)?;
+        }
+
+        // A stopped playbook will be resolved again when it's started.
+        if req.is_structural() && !playbook::is_stopped(&playbook) {
+            // The preface character is loaded again from the initial state.
+            let condition = if req.preface.is_some() { PlaybookState::pending() } else { PlaybookState::resolving() };
+            playbook::patch_status(&ctx.k8s, &playbook, condition).await.map_err(ApiError::ResourceError)?;
+            info!("The playbook {} was changed structurally, resolve it again", id);
+        }
+
+        Ok(playbook)
     }
 }
@@ -15,18 +15,30 @@
 use amp_common::resource;
 use amp_common::schema;
 
-use utoipa::OpenApi;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
 use utoipa_swagger_ui::SwaggerUi;
 
-use crate::{handlers, requests};
+use crate::{auth, errors, handlers, requests, responses};
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         handlers::actor::detail,
         handlers::actor::logs,
+        handlers::actor::builds,
+        handlers::actor::build_logs,
         handlers::actor::info,
         handlers::actor::stats,
+        handlers::actor::sync_changes,
+        handlers::actor::sync_ignore,
+        handlers::actor::sync_manifest,
+        handlers::actor::sync_status,
+        handlers::actor::delete,
+        handlers::actor::restart,
+        handlers::actor::rebuild,
+        handlers::actor::scale,
+        handlers::actor::rollback,
         //
         handlers::playbook::list,
         handlers::playbook::create,
@@ -40,6 +52,24 @@ use crate::{handlers, requests};
     ),
     components(
         schemas(
+            auth::Role,
+            errors::ErrorResponse,
+            requests::actor::ManifestEntry,
+            requests::actor::RollbackActorRequest,
+            requests::actor::ScaleActorRequest,
+            requests::actor::SyncManifestRequest,
+            requests::Sort,
+            responses::actor::ActorResponse,
+            responses::actor::RollbackResponse,
+            responses::actor::SyncIgnoreRules,
+            responses::actor::SyncOutcome,
+            responses::actor::SyncResponse,
+            responses::actor::SyncResult,
+            responses::actor::SyncStatus,
+            responses::playbook::PlaybookResponse,
+            responses::ConditionSummary,
+            responses::ResourceMeta,
+            responses::StatusSummary,
             requests::playbook::CreatePlaybookRequest,
             requests::playbook::UpdatePlaybookRequest,
             //
@@ -66,9 +96,23 @@ use crate::{handlers, requests};
         (name = "Actors", description = "The Actors Service Handlers"),
         (name = "Playbooks", description = "The Playbooks Service Handlers"),
     ),
+    modifiers(&SecurityAddon),
+    security(("bearer" = [])),
 )]
 struct ApiDoc;
 
+/// All the APIs are authenticated by the bearer token, see `auth::Authenticator`.
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            let scheme = HttpBuilder::new().scheme(HttpAuthScheme::Bearer).bearer_format("JWT").build();
+            components.add_security_scheme("bearer", SecurityScheme::Http(scheme));
+        }
+    }
+}
+
 pub fn build() -> SwaggerUi {
     SwaggerUi::new("/swagger").url("/openapi.json", ApiDoc::openapi())
 }
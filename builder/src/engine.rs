@@ -0,0 +1,87 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_common::resource::CharacterSpec;
+use tracing::warn;
+
+/// The build environment key to choose the buildpacks engine for a character,
+/// the value is one of `kpack` or `lifecycle`.
+pub const BUILDPACKS_ENGINE_KEY: &str = "AMP_BUILDPACKS_ENGINE";
+
+/// The engine to build images with Cloud Native Buildpacks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BuildpacksEngine {
+    /// Build with kpack, requires the kpack CRDs installed in the cluster.
+    #[default]
+    Kpack,
+    /// Run the CNB lifecycle `creator` directly in a Job.
+    Lifecycle,
+}
+
+impl BuildpacksEngine {
+    /// Returns the engine chosen by the character's build config, if any.
+    pub fn from_character(character: &CharacterSpec) -> Option<Self> {
+        let build = character.build.as_ref()?;
+        let value = build.env.as_ref()?.get(BUILDPACKS_ENGINE_KEY)?;
+
+        match value.to_lowercase().as_str() {
+            "kpack" => Some(BuildpacksEngine::Kpack),
+            "lifecycle" => Some(BuildpacksEngine::Lifecycle),
+            _ => {
+                warn!("Unknown buildpacks engine {}, fallback to the default", value);
+                None
+            }
+        }
+    }
+
+    /// Returns the engine chosen by the character, or the given default.
+    pub fn resolve(character: &CharacterSpec, default: BuildpacksEngine) -> Self {
+        Self::from_character(character).unwrap_or(default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use amp_common::resource::CharacterSpec;
+    use amp_common::schema::Build;
+
+    use super::*;
+
+    fn character(engine: &str) -> CharacterSpec {
+        CharacterSpec {
+            build: Some(Build {
+                env: Some(HashMap::from([(BUILDPACKS_ENGINE_KEY.to_string(), engine.to_string())])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_engine_from_character() {
+        assert_eq!(BuildpacksEngine::from_character(&character("lifecycle")), Some(BuildpacksEngine::Lifecycle));
+        assert_eq!(BuildpacksEngine::from_character(&character("Kpack")), Some(BuildpacksEngine::Kpack));
+        assert_eq!(BuildpacksEngine::from_character(&character("unknown")), None);
+        assert_eq!(BuildpacksEngine::from_character(&CharacterSpec::default()), None);
+    }
+
+    #[test]
+    fn test_engine_resolve_with_default() {
+        let default = BuildpacksEngine::Lifecycle;
+        assert_eq!(BuildpacksEngine::resolve(&CharacterSpec::default(), default), BuildpacksEngine::Lifecycle);
+        assert_eq!(BuildpacksEngine::resolve(&character("kpack"), default), BuildpacksEngine::Kpack);
+    }
+}
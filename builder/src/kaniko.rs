@@ -65,4 +65,9 @@ impl Builder for KanikoBuilder {
     async fn completed(&self) -> Result<bool> {
         job::completed(&self.k8s, &self.actor).await.map_err(Error::ResourceError)
     }
+
+    #[inline]
+    async fn failed(&self) -> Result<Option<String>> {
+        job::failed(&self.k8s, &self.actor).await.map_err(Error::ResourceError)
+    }
 }
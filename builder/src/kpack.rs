@@ -89,6 +89,16 @@ impl Builder for KpackBuilder {
     async fn completed(&self) -> Result<bool> {
         image::completed(&self.k8s, &self.actor).await.map_err(Error::ResourceError)
     }
+
+    #[inline]
+    async fn failed(&self) -> Result<Option<String>> {
+        image::failed(&self.k8s, &self.actor).await.map_err(Error::ResourceError)
+    }
+
+    #[inline]
+    async fn digest(&self) -> Result<Option<String>> {
+        image::digest(&self.k8s, &self.actor).await.map_err(Error::ResourceError)
+    }
 }
 
 impl KpackBuilder {
@@ -23,6 +23,9 @@ pub use kaniko::KanikoBuilder;
 mod kpack;
 pub use kpack::KpackBuilder;
 
+mod engine;
+pub use engine::{BuildpacksEngine, BUILDPACKS_ENGINE_KEY};
+
 pub mod errors;
 use errors::Result;
 
@@ -34,6 +37,12 @@ pub trait Builder: Send + Sync {
     async fn prepare(&self) -> Result<Option<Duration>>;
     async fn build(&self) -> Result<()>;
     async fn completed(&self) -> Result<bool>;
+    /// Returns the reason if the build failed, or None if it's running or succeeded.
+    async fn failed(&self) -> Result<Option<String>>;
+    /// Returns the digest of the built image, if the builder knows it.
+    async fn digest(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
 }
 
 /// Build director, it's a strategy pattern implementation
@@ -66,6 +75,16 @@ impl BuildDirector {
     pub async fn completed(&self) -> Result<bool> {
         self.builder.completed().await
     }
+
+    /// Check if the build is failed, and returns the reason
+    pub async fn failed(&self) -> Result<Option<String>> {
+        self.builder.failed().await
+    }
+
+    /// Returns the digest of the built image
+    pub async fn digest(&self) -> Result<Option<String>> {
+        self.builder.digest().await
+    }
 }
 
 #[cfg(test)]
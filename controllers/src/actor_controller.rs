@@ -56,6 +56,7 @@ pub async fn reconcile(actor: Arc<Actor>, ctx: Arc<Context>) -> Result<Action> {
             k8s: Arc::new(ctx.k8s.clone()),
             jetstream: ctx.jetstream.clone(),
             credentials: ctx.credentials.clone(),
+            buildpacks: ctx.buildpacks,
             object: actor.clone(),
         },
         Box::new(amp_workflow::actor::InitialState),
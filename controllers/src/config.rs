@@ -49,4 +49,23 @@ pub struct Config {
     /// Persistent Volume access mode, the default is `ReadWriteOnce`.
     #[clap(long, env = "AMP_PV_ACCESS_MODE", default_value = "ReadWriteOnce")]
     pub pv_access_mode: String,
+
+    /// The interval in seconds between two sweeps of the orphaned kpack
+    /// cluster objects, the default is `600`.
+    #[clap(long, env = "AMP_GC_INTERVAL", default_value_t = 600)]
+    pub gc_interval: u64,
+
+    /// The grace period in seconds that an orphaned kpack cluster object
+    /// is kept before deleting it, the default is `3600`.
+    #[clap(long, env = "AMP_GC_GRACE_PERIOD", default_value_t = 3600)]
+    pub gc_grace_period: u64,
+
+    /// Only report the orphaned kpack cluster objects without deleting them.
+    #[clap(long, env = "AMP_GC_DRY_RUN")]
+    pub gc_dry_run: bool,
+
+    /// The quiet period in seconds after the last synchronization of a live
+    /// actor before rebuilding it, `0` disables the rebuild, the default is `5`.
+    #[clap(long, env = "AMP_REBUILD_DEBOUNCE", default_value_t = 5)]
+    pub rebuild_debounce: u64,
 }
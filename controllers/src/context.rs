@@ -14,10 +14,12 @@
 
 use std::sync::Arc;
 
+use amp_builder::BuildpacksEngine;
 use amp_common::config::Credentials;
-use amp_resources::credential;
+use amp_resources::{credential, kpack};
 use async_nats::jetstream;
 use tokio::sync::RwLock;
+use tracing::{info, warn};
 
 use crate::config::Config;
 
@@ -33,7 +35,9 @@ pub struct Context {
     pub k8s: kube::Client,
     pub credentials: Arc<RwLock<Credentials>>,
     pub config: Arc<Config>,
+    pub nats: async_nats::Client,
     pub jetstream: Arc<jetstream::Context>,
+    pub buildpacks: BuildpacksEngine,
 }
 
 impl Context {
@@ -46,13 +50,28 @@ impl Context {
         let client = async_nats::connect(&config.nats_url)
             .await
             .map_err(|e| anyhow::anyhow!("Failed to connect to NATS: {}, {}", config.nats_url, e))?;
-        let jetstream = jetstream::new(client);
+        let jetstream = jetstream::new(client.clone());
+
+        // Build with the CNB lifecycle directly if kpack is not installed.
+        let buildpacks = match kpack::installed(&k8s).await {
+            Ok(true) => BuildpacksEngine::Kpack,
+            Ok(false) => {
+                info!("The kpack CRDs are not installed, build buildpacks images with lifecycle by default");
+                BuildpacksEngine::Lifecycle
+            }
+            Err(err) => {
+                warn!("Failed to check if kpack is installed, assume it is: {}", err);
+                BuildpacksEngine::Kpack
+            }
+        };
 
         Ok(Context {
             k8s,
             credentials: Arc::new(credentials),
             config: Arc::new(config),
+            nats: client,
             jetstream: Arc::new(jetstream),
+            buildpacks,
         })
     }
 }
@@ -0,0 +1,206 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use amp_resources::actor;
+use amp_resources::kpack::{cluster_builder, cluster_buildpack, cluster_store, References};
+use kube::Client;
+use tracing::{debug, error, info};
+
+use crate::context::Context;
+
+/// Sweeps the kpack cluster objects which are managed by Amphitheatre but no
+/// longer referenced by any live actor, they are deleted after the grace period.
+struct Sweeper {
+    grace_period: Duration,
+    dry_run: bool,
+    /// The time when each orphaned object is first found, keyed by kind and name.
+    first_seen: HashMap<(&'static str, String), Instant>,
+}
+
+pub async fn new(ctx: &Arc<Context>) {
+    let interval = Duration::from_secs(ctx.config.gc_interval);
+    let mut sweeper = Sweeper {
+        grace_period: Duration::from_secs(ctx.config.gc_grace_period),
+        dry_run: ctx.config.gc_dry_run,
+        first_seen: HashMap::new(),
+    };
+
+    info!("GC controller is running, dry run: {}", sweeper.dry_run);
+    loop {
+        if let Err(err) = sweeper.sweep(&ctx.k8s).await {
+            error!("Sweep orphaned kpack cluster objects failed: {}", err);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+impl Sweeper {
+    async fn sweep(&mut self, client: &Client) -> anyhow::Result<()> {
+        // List the managed objects before the actors, so that an object created
+        // in the meantime is always referenced by an actor in the list.
+        let managed = References {
+            builders: cluster_builder::list(client).await?.into_iter().collect(),
+            stores: cluster_store::list(client).await?.into_iter().collect(),
+            buildpacks: cluster_buildpack::list(client).await?.into_iter().collect(),
+        };
+
+        let actors = actor::list_all(client).await?;
+        let live = actors.iter().filter(|actor| actor.metadata.deletion_timestamp.is_none());
+        let orphans = managed.difference(&References::from_actors(live));
+
+        let expired = self.collect(&orphans, Instant::now());
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        // Delete the builders first, they reference the stores and buildpacks.
+        for name in &expired.builders {
+            cluster_builder::delete(client, name).await?;
+        }
+        for name in &expired.stores {
+            cluster_store::delete(client, name).await?;
+        }
+        for name in &expired.buildpacks {
+            cluster_buildpack::delete(client, name).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Tracks the orphaned objects, and returns the ones to delete now. Nothing is returned
+    /// in the dry run, the expired objects are reported only.
+    fn collect(&mut self, orphans: &References, now: Instant) -> References {
+        let expired = References {
+            builders: self.expired("ClusterBuilder", &orphans.builders, now),
+            stores: self.expired("ClusterStore", &orphans.stores, now),
+            buildpacks: self.expired("ClusterBuildpack", &orphans.buildpacks, now),
+        };
+
+        if self.dry_run && !expired.is_empty() {
+            info!("Dry run, would delete the orphaned kpack cluster objects: {:?}", expired);
+            return References::default();
+        }
+
+        expired
+    }
+
+    /// Tracks the orphaned objects of the kind, and returns the ones orphaned longer than the grace period.
+    fn expired(&mut self, kind: &'static str, orphans: &BTreeSet<String>, now: Instant) -> BTreeSet<String> {
+        // Forget the objects which are referenced again or already deleted.
+        self.first_seen.retain(|(k, name), _| *k != kind || orphans.contains(name));
+
+        let mut expired = BTreeSet::new();
+        for name in orphans {
+            let seen = *self.first_seen.entry((kind, name.clone())).or_insert_with(|| {
+                debug!("Found orphaned {} {}", kind, name);
+                now
+            });
+            if now.duration_since(seen) >= self.grace_period {
+                expired.insert(name.clone());
+            }
+        }
+
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRACE_PERIOD: Duration = Duration::from_secs(60);
+
+    fn sweeper(dry_run: bool) -> Sweeper {
+        Sweeper { grace_period: GRACE_PERIOD, dry_run, first_seen: HashMap::new() }
+    }
+
+    fn names(items: &[&str]) -> BTreeSet<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn test_expired_after_grace_period() {
+        let mut sweeper = sweeper(false);
+        let start = Instant::now();
+
+        // Within the grace period since it's first seen.
+        assert!(sweeper.expired("ClusterStore", &names(&["a"]), start).is_empty());
+        assert!(sweeper.expired("ClusterStore", &names(&["a", "b"]), start + GRACE_PERIOD / 2).is_empty());
+
+        // Past the grace period, counted from the first time of each object.
+        let expired = sweeper.expired("ClusterStore", &names(&["a", "b"]), start + GRACE_PERIOD);
+        assert_eq!(expired, names(&["a"]));
+        let expired = sweeper.expired("ClusterStore", &names(&["a", "b"]), start + GRACE_PERIOD * 2);
+        assert_eq!(expired, names(&["a", "b"]));
+    }
+
+    #[test]
+    fn test_expired_forgets_referenced() {
+        let mut sweeper = sweeper(false);
+        let start = Instant::now();
+        sweeper.expired("ClusterStore", &names(&["a"]), start);
+
+        // It's referenced again, so it's tracked from the beginning once orphaned again.
+        assert!(sweeper.expired("ClusterStore", &names(&[]), start + GRACE_PERIOD / 2).is_empty());
+        assert!(sweeper.first_seen.is_empty());
+        assert!(sweeper.expired("ClusterStore", &names(&["a"]), start + GRACE_PERIOD).is_empty());
+        let expired = sweeper.expired("ClusterStore", &names(&["a"]), start + GRACE_PERIOD * 2);
+        assert_eq!(expired, names(&["a"]));
+    }
+
+    #[test]
+    fn test_expired_by_kind() {
+        let mut sweeper = sweeper(false);
+        let start = Instant::now();
+        sweeper.expired("ClusterStore", &names(&["a"]), start);
+        sweeper.expired("ClusterBuildpack", &names(&["a"]), start + GRACE_PERIOD);
+
+        // The objects of another kind with the same name are tracked separately.
+        assert_eq!(sweeper.first_seen.len(), 2);
+        let expired = sweeper.expired("ClusterBuildpack", &names(&["a"]), start + GRACE_PERIOD);
+        assert!(expired.is_empty());
+        let expired = sweeper.expired("ClusterStore", &names(&["a"]), start + GRACE_PERIOD);
+        assert_eq!(expired, names(&["a"]));
+
+        // Forgetting the objects of a kind keeps the others.
+        sweeper.expired("ClusterBuildpack", &names(&[]), start + GRACE_PERIOD);
+        assert_eq!(sweeper.first_seen.keys().collect::<Vec<_>>(), vec![&("ClusterStore", "a".to_string())]);
+    }
+
+    #[test]
+    fn test_collect() {
+        let orphans = References { builders: names(&["b"]), stores: names(&["s"]), buildpacks: names(&["p"]) };
+        let start = Instant::now();
+
+        let mut sweeper = sweeper(false);
+        assert!(sweeper.collect(&orphans, start).is_empty());
+        assert_eq!(sweeper.collect(&orphans, start + GRACE_PERIOD), orphans);
+    }
+
+    #[test]
+    fn test_collect_dry_run() {
+        let orphans = References { builders: names(&["b"]), ..Default::default() };
+        let start = Instant::now();
+
+        // Nothing is deleted in the dry run, but the orphans are still tracked.
+        let mut sweeper = sweeper(true);
+        assert!(sweeper.collect(&orphans, start).is_empty());
+        assert!(sweeper.collect(&orphans, start + GRACE_PERIOD).is_empty());
+        assert_eq!(sweeper.first_seen.get(&("ClusterBuilder", "b".to_string())), Some(&start));
+    }
+}
@@ -30,8 +30,10 @@ use crate::context::Context;
 
 mod actor_controller;
 mod credentials_watcher;
+mod gc_controller;
 mod namespace_watcher;
 mod playbook_controller;
+mod sync_watcher;
 mod timeout_controller;
 
 #[tokio::main]
@@ -58,7 +60,9 @@ async fn main() -> anyhow::Result<()> {
         _ = actor_controller::new(&ctx) => tracing::warn!("actor controller exited"),
         _ = credentials_watcher::new(&ctx) => tracing::warn!("credentials watcher exited"),
         _ = namespace_watcher::new(&ctx) => tracing::warn!("namespace watcher exited"),
-        _ = timeout_controller::new(&ctx) => tracing::warn!("timeout controller exited")
+        _ = sync_watcher::new(&ctx) => tracing::warn!("sync watcher exited"),
+        _ = timeout_controller::new(&ctx) => tracing::warn!("timeout controller exited"),
+        _ = gc_controller::new(&ctx) => tracing::warn!("gc controller exited")
     }
 
     Ok(())
@@ -56,6 +56,7 @@ pub async fn reconcile(playbook: Arc<Playbook>, ctx: Arc<Context>) -> Result<Act
             k8s: Arc::new(ctx.k8s.clone()),
             jetstream: ctx.jetstream.clone(),
             credentials: ctx.credentials.clone(),
+            buildpacks: ctx.buildpacks,
             object: playbook.clone(),
         },
         Box::new(amp_workflow::playbook::InitialState),
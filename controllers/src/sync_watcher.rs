@@ -0,0 +1,308 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use amp_builder::BuildpacksEngine;
+use amp_common::resource::ActorState;
+use amp_common::schema::BuildMethod;
+use amp_resources::containers::devcontainer::DevContainer;
+use amp_resources::hot_reload::{self, HotReload};
+use amp_resources::sync::{self, Outcome, SyncResult, DEAD_LETTER_ACTOR, DEAD_LETTER_REASON};
+use amp_resources::{actor, build, job, kpack::image, volume};
+use async_nats::Message;
+use futures::StreamExt;
+use tracing::{debug, error, info, warn};
+
+use crate::context::Context;
+
+/// The max number of touched paths kept for a pending rebuild, it's considered as touching everything if exceeded.
+const MAX_PATHS: usize = 1000;
+
+/// Watches the dead letters and results of the syncers, marks the actor as out of sync
+/// when its synchronization is dead-lettered, until its workspace is overwritten.
+/// And rebuilds the live actors once their synchronizations are quiet for a while.
+pub async fn new(ctx: &Arc<Context>) {
+    let subscriptions = tokio::try_join!(
+        ctx.nats.subscribe(sync::dead_letter_subject("*")),
+        ctx.nats.subscribe(sync::results_subject("*", "*"))
+    );
+    let (mut dead_letters, mut results) = match subscriptions {
+        Ok(subscriptions) => subscriptions,
+        Err(err) => {
+            error!("Failed to subscribe the syncer subjects: {}", err);
+            return;
+        }
+    };
+    info!("Sync watcher is running...");
+
+    let mut debouncer = Debouncer::new(Duration::from_secs(ctx.config.rebuild_debounce));
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+
+    loop {
+        tokio::select! {
+            Some(message) = dead_letters.next() => {
+                if let Err(err) = dead_lettered(ctx, &message).await {
+                    error!("Handle dead letter failed: {}", err);
+                }
+            }
+            Some(message) = results.next() => {
+                let result = match serde_json::from_slice::<SyncResult>(&message.payload) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        warn!("Received invalid sync result on subject {}: {}", message.subject, err);
+                        continue;
+                    }
+                };
+                if let Err(err) = resynced(ctx, &message.subject, &result).await {
+                    error!("Handle sync result failed: {}", err);
+                }
+                if let Some((playbook, name, paths)) = applied(&message.subject, result) {
+                    debouncer.push(&playbook, &name, paths, Instant::now());
+                }
+            }
+            _ = ticker.tick() => {
+                for ((playbook, name), paths) in debouncer.expired(Instant::now()) {
+                    if let Err(err) = rebuild(ctx, &playbook, &name, paths).await {
+                        error!("Rebuild Actor {} after synchronization failed: {}", name, err);
+                    }
+                }
+            }
+            else => break,
+        }
+    }
+}
+
+/// Mark the actor as out of sync with the reason of the dead letter.
+async fn dead_lettered(ctx: &Arc<Context>, message: &Message) -> anyhow::Result<()> {
+    let headers = message.headers.as_ref();
+    let Some(name) = headers.and_then(|headers| headers.get(DEAD_LETTER_ACTOR)) else {
+        warn!("Received dead letter without actor on subject {}", message.subject);
+        return Ok(());
+    };
+    let reason = headers.and_then(|headers| headers.get(DEAD_LETTER_REASON)).map_or("", |value| value.as_str());
+    let playbook = message.subject.split('.').next().unwrap_or_default();
+
+    let actor = actor::get(&ctx.k8s, &format!("amp-{playbook}"), name.as_str()).await?;
+    let text = format!("The synchronization is dead-lettered, the workspace may be out of sync: {reason}");
+    actor::set_condition(&ctx.k8s, &actor, actor::out_of_sync(&text)).await?;
+
+    Ok(())
+}
+
+/// Clear the out of sync mark once the workspace is overwritten.
+async fn resynced(ctx: &Arc<Context>, subject: &str, result: &SyncResult) -> anyhow::Result<()> {
+    if result.outcome != Outcome::Applied || result.kind.as_deref() != Some("Overwrite") {
+        return Ok(());
+    }
+    let Some((playbook, name)) = actor_of(subject) else {
+        return Ok(());
+    };
+
+    let actor = actor::get(&ctx.k8s, &format!("amp-{playbook}"), name).await?;
+    if actor::is_out_of_sync(&actor) {
+        debug!("The workspace of Actor {} is overwritten", name);
+        actor::set_condition(&ctx.k8s, &actor, actor::resynced()).await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the playbook and actor of the applied result on the subject `{playbook}.{actor}.results`,
+/// with the paths it touched, None if it's not applied.
+fn applied(subject: &str, result: SyncResult) -> Option<(String, String, Option<Vec<String>>)> {
+    if result.outcome != Outcome::Applied {
+        return None;
+    }
+    let (playbook, name) = actor_of(subject)?;
+
+    Some((playbook.to_string(), name.to_string(), result.paths))
+}
+
+/// Returns the playbook and actor of the subject `{playbook}.{actor}.*`.
+fn actor_of(subject: &str) -> Option<(&str, &str)> {
+    let mut tokens = subject.split('.');
+    Some((tokens.next()?, tokens.next()?))
+}
+
+/// Rebuild or reload the live actor if the synchronized paths are inputs of its build.
+async fn rebuild(ctx: &Arc<Context>, playbook: &str, name: &str, paths: Option<Vec<String>>) -> anyhow::Result<()> {
+    let actor = actor::get(&ctx.k8s, &format!("amp-{playbook}"), name).await?;
+    if !actor.spec.live {
+        return Ok(());
+    }
+    // The pending or building actor is going to build with the synchronized sources anyway.
+    if !actor.status.as_ref().is_some_and(|status| status.running()) {
+        debug!("Actor {} is not running, skip rebuilding", name);
+        return Ok(());
+    }
+    // The development happens inside the devcontainer, with the synchronized workspace.
+    if DevContainer::from_actor(&actor).is_some() {
+        return Ok(());
+    }
+    if paths.is_some_and(|paths| !actor::build_inputs_touched(&actor, &paths)) {
+        debug!("The build inputs of Actor {} are not touched, skip rebuilding", name);
+        return Ok(());
+    }
+
+    // Reload the application instead if it runs in the workspace directly, see `DeployTask`.
+    if let Some(config) = HotReload::from_spec(&actor.spec) {
+        if volume::exists(&ctx.k8s, &actor).await? {
+            hot_reload::reload(&ctx.k8s, &actor, &config).await?;
+            info!("Reloaded Actor {} in playbook {} after synchronization", name, playbook);
+            return Ok(());
+        }
+    }
+
+    // kpack rebuilds the Image once triggered, the others are rebuilt by a new Job.
+    let build = actor.spec.character.build.clone().unwrap_or_default();
+    let kpack = matches!(build.method(), BuildMethod::Buildpacks)
+        && BuildpacksEngine::resolve(&actor.spec.character, ctx.buildpacks) == BuildpacksEngine::Kpack;
+    if kpack {
+        image::trigger(&ctx.k8s, &actor).await?;
+    } else {
+        job::delete(&ctx.k8s, &actor).await?;
+        build::cancel(&ctx.k8s, &actor, "Cancelled by a rebuild after synchronization").await?;
+    }
+
+    actor::patch_status(&ctx.k8s, &actor, ActorState::building()).await?;
+    info!("Rebuilding Actor {} in playbook {} after synchronization", name, playbook);
+
+    Ok(())
+}
+
+/// Collects the applied synchronizations of each actor, and releases them
+/// after no more synchronization of the actor is applied for the delay.
+struct Debouncer {
+    delay: Duration,
+    pending: HashMap<(String, String), Pending>,
+}
+
+struct Pending {
+    deadline: Instant,
+    /// The touched paths since the first synchronization, None if they are unknown or too many.
+    paths: Option<Vec<String>>,
+}
+
+impl Debouncer {
+    fn new(delay: Duration) -> Self {
+        Debouncer { delay, pending: HashMap::new() }
+    }
+
+    /// Record the applied synchronization of the actor, it's disabled if the delay is zero.
+    fn push(&mut self, playbook: &str, name: &str, paths: Option<Vec<String>>, now: Instant) {
+        if self.delay.is_zero() {
+            return;
+        }
+
+        let deadline = now + self.delay;
+        let key = (playbook.to_string(), name.to_string());
+        let pending = self.pending.entry(key).or_insert_with(|| Pending { deadline, paths: Some(vec![]) });
+        pending.deadline = deadline;
+        match (pending.paths.as_mut(), paths) {
+            (Some(pending), Some(paths)) if pending.len() + paths.len() <= MAX_PATHS => pending.extend(paths),
+            _ => pending.paths = None,
+        }
+    }
+
+    /// Take the actors whose synchronizations are quiet for the delay.
+    fn expired(&mut self, now: Instant) -> Vec<((String, String), Option<Vec<String>>)> {
+        let keys: Vec<_> = self.pending.iter().filter(|(_, p)| p.deadline <= now).map(|(k, _)| k.clone()).collect();
+
+        keys.into_iter().filter_map(|key| self.pending.remove_entry(&key)).map(|(key, p)| (key, p.paths)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_secs(10);
+
+    fn paths(items: &[&str]) -> Option<Vec<String>> {
+        Some(items.iter().map(|item| item.to_string()).collect())
+    }
+
+    fn key(name: &str) -> (String, String) {
+        ("pid".to_string(), name.to_string())
+    }
+
+    #[test]
+    fn test_debounce() {
+        let mut debouncer = Debouncer::new(DELAY);
+        let start = Instant::now();
+        debouncer.push("pid", "web", paths(&["src/main.rs"]), start);
+        debouncer.push("pid", "api", paths(&["go.mod"]), start);
+
+        assert!(debouncer.expired(start + DELAY / 2).is_empty());
+        let mut expired = debouncer.expired(start + DELAY);
+        expired.sort();
+        assert_eq!(expired, vec![(key("api"), paths(&["go.mod"])), (key("web"), paths(&["src/main.rs"]))]);
+        assert!(debouncer.expired(start + DELAY * 2).is_empty());
+    }
+
+    #[test]
+    fn test_debounce_extends_deadline() {
+        let mut debouncer = Debouncer::new(DELAY);
+        let start = Instant::now();
+
+        // A burst of synchronizations is released once, after the last one is quiet for the delay.
+        debouncer.push("pid", "web", paths(&["a"]), start);
+        debouncer.push("pid", "web", paths(&["b"]), start + DELAY / 2);
+        assert!(debouncer.expired(start + DELAY).is_empty());
+        assert_eq!(debouncer.expired(start + DELAY * 3 / 2), vec![(key("web"), paths(&["a", "b"]))]);
+    }
+
+    #[test]
+    fn test_debounce_too_many_paths() {
+        let mut debouncer = Debouncer::new(DELAY);
+        let start = Instant::now();
+
+        let many: Vec<String> = (0..MAX_PATHS).map(|index| format!("src/{index}.rs")).collect();
+        debouncer.push("pid", "web", Some(many), start);
+        debouncer.push("pid", "web", paths(&["one-more"]), start);
+        assert_eq!(debouncer.expired(start + DELAY), vec![(key("web"), None)]);
+
+        // The unknown paths are considered as touching everything.
+        debouncer.push("pid", "web", paths(&["a"]), start);
+        debouncer.push("pid", "web", None, start);
+        debouncer.push("pid", "web", paths(&["b"]), start);
+        assert_eq!(debouncer.expired(start + DELAY), vec![(key("web"), None)]);
+    }
+
+    #[test]
+    fn test_debounce_disabled() {
+        let mut debouncer = Debouncer::new(Duration::ZERO);
+        let start = Instant::now();
+
+        debouncer.push("pid", "web", paths(&["a"]), start);
+        assert!(debouncer.pending.is_empty());
+        assert!(debouncer.expired(start + DELAY).is_empty());
+    }
+
+    #[test]
+    fn test_applied() {
+        let result = |outcome: Outcome| SyncResult { sequence: 1, kind: None, outcome, paths: paths(&["a"]) };
+
+        let expected = Some(("pid".to_string(), "web".to_string(), paths(&["a"])));
+        assert_eq!(applied("pid.web.results", result(Outcome::Applied)), expected);
+
+        assert_eq!(applied("pid.web.results", result(Outcome::Failed { reason: "denied".into() })), None);
+        assert_eq!(applied("pid.web.results", result(Outcome::Skipped { reason: "ignored".into() })), None);
+        assert_eq!(applied("pid.web.results", result(Outcome::DeadLettered { reason: "poison".into() })), None);
+        assert_eq!(applied("pid", result(Outcome::Applied)), None);
+    }
+}
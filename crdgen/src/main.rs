@@ -19,6 +19,7 @@ use std::io::Write;
 use std::path::Path;
 
 use amp_common::resource::{Actor, Character, Playbook};
+use amp_resources::build::BuildRecord;
 use clap::Parser;
 use kube::CustomResourceExt;
 use serde::Serialize;
@@ -41,6 +42,7 @@ struct Args {
 fn main() {
     let mappings = HashMap::from([
         ("actor", ("actor.yaml", Actor::crd())),
+        ("buildrecord", ("buildrecord.yaml", BuildRecord::crd())),
         ("character", ("character.yaml", Character::crd())),
         ("playbook", ("playbook.yaml", Playbook::crd())),
     ]);
@@ -119,7 +121,7 @@ mod test {
             .expect("failed to execute process");
 
         let stdout = String::from_utf8(output.stdout).unwrap();
-        assert_eq!(stdout, "actor\ncharacter\nplaybook\n");
+        assert_eq!(stdout, "actor\nbuildrecord\ncharacter\nplaybook\n");
     }
 
     #[test]
@@ -188,5 +190,6 @@ mod test {
         assert!(stdout.contains("actors.amphitheatre.app"));
         assert!(stdout.contains("characters.amphitheatre.app"));
         assert!(stdout.contains("playbooks.amphitheatre.app"));
+        assert!(stdout.contains("buildrecords.amphitheatre.app"));
     }
 }
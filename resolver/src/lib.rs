@@ -17,6 +17,7 @@ use amp_common::schema::{Character, GitReference};
 use amp_common::scm::client::Client as ScmClient;
 use amp_common::{config::Credentials, resource::ActorSpec};
 use amp_resources::character;
+use amp_resources::containers::devcontainer::DevContainer;
 use errors::{ResolveError, Result};
 use kube::Client as KubeClient;
 use tracing::debug;
@@ -60,6 +61,29 @@ pub async fn load_from_source(credentials: &Credentials, reference: &GitReferenc
     Ok(CharacterSpec::from(&manifest))
 }
 
+/// Load the `devcontainer.json` from the character repository.
+pub async fn load_devcontainer(
+    credentials: &Credentials,
+    character: &CharacterSpec,
+    path: &str,
+) -> Result<DevContainer> {
+    let reference = GitReference { repo: character.meta.repository.clone(), ..GitReference::default() };
+    let client = ScmClient::init(credentials, &reference.repo).map_err(ResolveError::SCMError)?;
+
+    let reference = patches::source(&client, &reference).await?;
+    let repo = utils::repo(&reference.repo)?;
+
+    let content = client
+        .contents()
+        .find(&repo, path, &reference.rev())
+        .await
+        .map_err(|e| ResolveError::FetchingError(e.to_string()))?;
+    let data = std::str::from_utf8(&content.data).map_err(ResolveError::ConvertBytesError)?;
+    debug!("The `{}` content of {} is:\n{:?}", path, repo, data);
+
+    DevContainer::parse(data).map_err(ResolveError::ResourceError)
+}
+
 /// Load manifest from Kubernetes cluster and return the actor spec.
 pub async fn load_from_cluster(client: &KubeClient, name: &str) -> Result<CharacterSpec> {
     let character = character::get(client, name).await.map_err(ResolveError::ResourceError)?;
@@ -12,16 +12,35 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::path::{Component, Path, PathBuf};
+
+use super::condition;
 use super::error::{Error, Result};
 
 use amp_common::resource::{Actor, ActorSpec, ActorState, Playbook};
 use k8s_metrics::v1beta1::PodMetrics;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
-use kube::api::{ListParams, Patch, PatchParams, PostParams};
+use kube::api::{DeleteParams, ListParams, Patch, PatchParams, PostParams};
+use kube::core::ObjectList;
 use kube::{Api, Client, Resource, ResourceExt};
 use serde_json::json;
 use tracing::{debug, error, info};
 
+/// The condition type of an actor that failed and will not be retried automatically.
+pub const FAILED: &str = "Failed";
+
+/// The condition type of an actor whose workspace may be out of sync with the client,
+/// because some synchronizations are dead-lettered.
+pub const OUT_OF_SYNC: &str = "OutOfSync";
+
+/// The annotation of the image an actor is rolled back to, it's deployed instead of
+/// the built one until the next successful build.
+pub const ROLLBACK_IMAGE_ANNOTATION: &str = "amphitheatre.app/rollback-image";
+
+/// The annotation of the image reference by digest of the last successful build, e.g.
+/// `registry/app:tag@sha256:...`, so that a rebuild of the same tag is rolled out.
+pub const BUILT_IMAGE_ANNOTATION: &str = "amphitheatre.app/built-image";
+
 pub async fn exists(client: &Client, playbook: &Playbook, name: &str) -> Result<bool> {
     let namespace = playbook.spec.namespace();
     let api: Api<Actor> = Api::namespaced(client.clone(), namespace.as_str());
@@ -86,6 +105,54 @@ pub async fn patch_status(client: &Client, actor: &Actor, condition: Condition)
     Ok(())
 }
 
+/// Set the annotation of the actor, it's kept when the actor is updated.
+pub async fn annotate(client: &Client, actor: &Actor, key: &str, value: &str) -> Result<()> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<Actor> = Api::namespaced(client.clone(), &namespace);
+
+    let patch = json!({ "metadata": { "annotations": { key: value } } });
+    api.patch(&actor.name_any(), &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+    debug!("Annotated {} for Actor {}", key, actor.name_any());
+
+    Ok(())
+}
+
+/// Remove the annotation of the actor, if any.
+pub async fn remove_annotation(client: &Client, actor: &Actor, key: &str) -> Result<()> {
+    if !actor.annotations().contains_key(key) {
+        return Ok(());
+    }
+
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<Actor> = Api::namespaced(client.clone(), &namespace);
+
+    let patch = json!({ "metadata": { "annotations": { key: null } } });
+    api.patch(&actor.name_any(), &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+    debug!("Removed annotation {} for Actor {}", key, actor.name_any());
+
+    Ok(())
+}
+
+/// Set the condition of the actor, replacing the one of the same type and keeping the others.
+pub async fn set_condition(client: &Client, actor: &Actor, condition: Condition) -> Result<()> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<Actor> = Api::namespaced(client.clone(), &namespace);
+
+    let mut conditions: Vec<Condition> =
+        actor.status.as_ref().map(|status| status.conditions.clone()).unwrap_or_default();
+    conditions.retain(|c| c.type_ != condition.type_);
+    conditions.push(condition.clone());
+
+    let status = json!({ "status": { "conditions": conditions }});
+    api.patch_status(actor.name_any().as_str(), &PatchParams::default(), &Patch::Merge(&status))
+        .await
+        .map_err(Error::KubeError)?;
+
+    info!("Set condition {:?} with reason {:?} for Actor {}", condition.type_, condition.reason, actor.name_any());
+
+    Ok(())
+}
+
 pub async fn metrics(client: &Client, namespace: &str, name: &str) -> Result<PodMetrics> {
     let api: Api<PodMetrics> = Api::namespaced(client.clone(), namespace);
     let params = ListParams::default().labels(&format!("amphitheatre.app/character={name}")).limit(1);
@@ -113,9 +180,164 @@ pub async fn get(client: &Client, namespace: &str, name: &str) -> Result<Actor>
     api.get(name).await.map_err(Error::KubeError)
 }
 
-pub async fn list(client: &Client, namespace: &str) -> Result<Vec<Actor>> {
+/// List the actors of a playbook, the params carry the label selector and the continuation.
+pub async fn list(client: &Client, namespace: &str, params: &ListParams) -> Result<ObjectList<Actor>> {
     let api: Api<Actor> = Api::namespaced(client.clone(), namespace);
+    api.list(params).await.map_err(Error::KubeError)
+}
+
+/// List the actors of all playbooks
+pub async fn list_all(client: &Client) -> Result<Vec<Actor>> {
+    let api: Api<Actor> = Api::all(client.clone());
     let actors = api.list(&ListParams::default()).await.map_err(Error::KubeError)?;
 
     Ok(actors.items)
 }
+
+/// Delete an actor by name
+pub async fn delete(client: &Client, namespace: &str, name: &str) -> Result<()> {
+    let api: Api<Actor> = Api::namespaced(client.clone(), namespace);
+    api.delete(name, &DeleteParams::default()).await.map_err(Error::KubeError)?;
+    info!("Deleted Actor: {}", name);
+
+    Ok(())
+}
+
+/// Returns the condition of an actor failed to build, with the reason reported by the builder.
+pub fn build_failed(message: &str) -> Condition {
+    condition(FAILED, "BuildFailed", message.to_string())
+}
+
+/// Returns the condition of an actor whose devcontainer can't be run, with the reason.
+pub fn devcontainer_unsupported(message: &str) -> Condition {
+    condition(FAILED, "UnsupportedDevContainer", message.to_string())
+}
+
+/// Check if the actor is failed, it will stay failed until it's rebuilt.
+pub fn is_failed(actor: &Actor) -> bool {
+    actor
+        .status
+        .as_ref()
+        .is_some_and(|status| status.conditions.iter().any(|c| c.type_ == FAILED && c.status == "True"))
+}
+
+/// Returns the condition of an actor whose synchronization is dead-lettered.
+pub fn out_of_sync(message: &str) -> Condition {
+    condition(OUT_OF_SYNC, "DeadLettered", message.to_string())
+}
+
+/// Returns the condition of an actor whose workspace is overwritten after it's out of sync.
+pub fn resynced() -> Condition {
+    Condition { status: "False".into(), ..condition(OUT_OF_SYNC, "Resynced", "The workspace is overwritten".into()) }
+}
+
+/// Check if the actor's workspace may be out of sync.
+pub fn is_out_of_sync(actor: &Actor) -> bool {
+    actor
+        .status
+        .as_ref()
+        .is_some_and(|status| status.conditions.iter().any(|c| c.type_ == OUT_OF_SYNC && c.status == "True"))
+}
+
+/// Returns the image the actor is rolled back to, if any.
+pub fn rollback_image(actor: &Actor) -> Option<&str> {
+    actor.annotations().get(ROLLBACK_IMAGE_ANNOTATION).map(String::as_str).filter(|image| !image.is_empty())
+}
+
+/// Returns the image of the last successful build pinned by its digest, if it's built from the current image.
+pub fn built_image(actor: &Actor) -> Option<&str> {
+    let image = actor.annotations().get(BUILT_IMAGE_ANNOTATION)?;
+    image.strip_prefix(actor.spec.image.as_str()).filter(|digest| digest.starts_with('@')).map(|_| image.as_str())
+}
+
+/// Returns the image deployed for the actor, the rolled back one takes precedence over the built one.
+pub fn deployed_image(actor: &Actor) -> &str {
+    rollback_image(actor).or_else(|| built_image(actor)).unwrap_or(&actor.spec.image)
+}
+
+/// Check if any of the synchronized paths (relative to the workspace) is an input of the
+/// actor's build, that is, it's inside the build context.
+pub fn build_inputs_touched(actor: &Actor, paths: &[String]) -> bool {
+    let context = actor.spec.character.build.as_ref().and_then(|build| build.context.as_deref()).unwrap_or(".");
+    let context = relative(Path::new(context));
+
+    paths.iter().any(|path| relative(Path::new(path)).starts_with(&context))
+}
+
+/// Strip the root and current directory components, e.g. `./src` and `/src` are both `src`.
+fn relative(path: &Path) -> PathBuf {
+    path.components().filter(|c| matches!(c, Component::Normal(_) | Component::ParentDir)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_common::schema::Build;
+
+    use super::*;
+
+    #[test]
+    fn test_deployed_image() {
+        let mut actor = Actor::new("test", ActorSpec { image: "registry/app:v2".into(), ..ActorSpec::default() });
+        assert_eq!(rollback_image(&actor), None);
+        assert_eq!(deployed_image(&actor), "registry/app:v2");
+
+        actor.annotations_mut().insert(BUILT_IMAGE_ANNOTATION.into(), "registry/app:v2@sha256:1234".into());
+        assert_eq!(built_image(&actor), Some("registry/app:v2@sha256:1234"));
+        assert_eq!(deployed_image(&actor), "registry/app:v2@sha256:1234");
+
+        actor.annotations_mut().insert(ROLLBACK_IMAGE_ANNOTATION.into(), "registry/app:v1".into());
+        assert_eq!(rollback_image(&actor), Some("registry/app:v1"));
+        assert_eq!(deployed_image(&actor), "registry/app:v1");
+    }
+
+    #[test]
+    fn test_built_image() {
+        let mut actor = Actor::new("test", ActorSpec { image: "registry/app:v2".into(), ..ActorSpec::default() });
+        assert_eq!(built_image(&actor), None);
+
+        // The digest of the image built before the image is changed.
+        actor.annotations_mut().insert(BUILT_IMAGE_ANNOTATION.into(), "registry/app:v1@sha256:1234".into());
+        assert_eq!(built_image(&actor), None);
+        assert_eq!(deployed_image(&actor), "registry/app:v2");
+
+        actor.annotations_mut().insert(BUILT_IMAGE_ANNOTATION.into(), "registry/app:v2-rc@sha256:1234".into());
+        assert_eq!(built_image(&actor), None);
+    }
+
+    #[test]
+    fn test_is_failed() {
+        let mut actor = Actor::new("test", ActorSpec::default());
+        assert!(!is_failed(&actor));
+
+        actor.status = Some(Default::default());
+        actor.status.as_mut().unwrap().conditions = vec![build_failed("The build Job test-builder failed")];
+        assert!(is_failed(&actor));
+
+        actor.status.as_mut().unwrap().conditions = vec![ActorState::building()];
+        assert!(!is_failed(&actor));
+    }
+
+    #[test]
+    fn test_is_out_of_sync() {
+        let mut actor = Actor::new("test", ActorSpec::default());
+        actor.status = Some(Default::default());
+        actor.status.as_mut().unwrap().conditions = vec![ActorState::running(true, "AutoRun", None), out_of_sync("")];
+        assert!(is_out_of_sync(&actor));
+
+        actor.status.as_mut().unwrap().conditions = vec![ActorState::running(true, "AutoRun", None), resynced()];
+        assert!(!is_out_of_sync(&actor));
+    }
+
+    #[test]
+    fn test_build_inputs_touched() {
+        let paths = vec!["docs/README.md".to_string()];
+        let mut actor = Actor::new("test", ActorSpec::default());
+        assert!(build_inputs_touched(&actor, &paths));
+
+        actor.spec.character.build = Some(Build { context: Some("./app".into()), ..Default::default() });
+        assert!(!build_inputs_touched(&actor, &paths));
+        assert!(build_inputs_touched(&actor, &["app/src/main.rs".to_string()]));
+        assert!(!build_inputs_touched(&actor, &["application.rs".to_string()]));
+        assert!(!build_inputs_touched(&actor, &[]));
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use amp_common::resource::Actor;
+use jiff::Timestamp;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+use kube::api::{DeleteParams, ListParams, Patch, PatchParams, PostParams};
+use kube::core::ObjectMeta;
+use kube::{Api, Client, CustomResource, Resource, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::{debug, info};
+
+use crate::error::{Error, Result};
+
+/// The max number of build records kept for each actor, the oldest ones are deleted.
+const MAX_RECORDS: usize = 20;
+
+/// The record of a single build of an actor.
+#[derive(CustomResource, Clone, Debug, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[kube(
+    group = "amphitheatre.app",
+    version = "v1",
+    kind = "BuildRecord",
+    namespaced,
+    printcolumn = r#"{"name":"Actor", "type":"string", "jsonPath":".spec.actor"}"#,
+    printcolumn = r#"{"name":"Builder", "type":"string", "jsonPath":".spec.builder"}"#,
+    printcolumn = r#"{"name":"Outcome", "type":"string", "jsonPath":".spec.outcome"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildRecordSpec {
+    /// The name of the built actor.
+    pub actor: String,
+    /// The source revision, e.g. the git commit, None for the live synchronized sources.
+    pub revision: Option<String>,
+    /// The image (tag) to build.
+    pub image: String,
+    /// The digest of the built image, if known.
+    pub digest: Option<String>,
+    /// The builder used for this build, one of `kaniko`, `kpack` or `lifecycle`.
+    pub builder: String,
+    pub started_at: Option<Time>,
+    pub finished_at: Option<Time>,
+    /// The duration of the build in seconds.
+    pub duration: Option<i64>,
+    pub outcome: BuildOutcome,
+    /// The reason of the failed build.
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, JsonSchema, PartialEq, Eq)]
+pub enum BuildOutcome {
+    #[default]
+    Running,
+    Succeeded,
+    Failed,
+    /// The build is abandoned before it finishes, e.g. its Job is deleted to rebuild.
+    Cancelled,
+}
+
+/// Record the start of a build, it's a no-op if the same build of the actor is already recorded.
+/// The running record of another build is left behind, e.g. by a crash, so it's cancelled.
+pub async fn start(client: &Client, actor: &Actor, builder: &str) -> Result<BuildRecord> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<BuildRecord> = Api::namespaced(client.clone(), namespace.as_str());
+
+    let resource = new(actor, builder, Timestamp::now());
+    if let Some(record) = running(client, actor).await? {
+        if record.spec.same_build(&resource.spec) {
+            debug!("The build of Actor {} is already recorded as {}", actor.name_any(), record.name_any());
+            return Ok(record);
+        }
+        close(&api, &record, BuildOutcome::Cancelled, None, Some("Superseded by another build".into())).await?;
+    }
+
+    let record = api.create(&PostParams::default(), &resource).await.map_err(Error::KubeError)?;
+    info!("Created BuildRecord: {}", record.name_any());
+
+    // Delete the oldest records
+    for item in outdated(list(client, &namespace, &actor.spec.name).await?) {
+        api.delete(&item.name_any(), &DeleteParams::default()).await.map_err(Error::KubeError)?;
+        debug!("Deleted the outdated BuildRecord: {}", item.name_any());
+    }
+
+    Ok(record)
+}
+
+/// Record the outcome of the running build, the digest is available if succeeded,
+/// and the message is the reason if failed.
+pub async fn finish(
+    client: &Client,
+    actor: &Actor,
+    outcome: BuildOutcome,
+    digest: Option<String>,
+    message: Option<String>,
+) -> Result<()> {
+    let Some(record) = running(client, actor).await? else {
+        debug!("No running build recorded for Actor {}", actor.name_any());
+        return Ok(());
+    };
+
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<BuildRecord> = Api::namespaced(client.clone(), namespace.as_str());
+    close(&api, &record, outcome, digest, message).await?;
+
+    info!("Recorded the build {} of Actor {} as {:?}", record.name_any(), actor.name_any(), outcome);
+    Ok(())
+}
+
+/// Record the running build as cancelled with the reason, if any, so that the next build is
+/// recorded on its own. It's called when the build is deleted before it finishes.
+pub async fn cancel(client: &Client, actor: &Actor, reason: &str) -> Result<()> {
+    finish(client, actor, BuildOutcome::Cancelled, None, Some(reason.to_string())).await
+}
+
+/// List the build records of the actor, the newest first.
+pub async fn list(client: &Client, namespace: &str, name: &str) -> Result<Vec<BuildRecord>> {
+    let api: Api<BuildRecord> = Api::namespaced(client.clone(), namespace);
+    let params = ListParams::default().labels(&format!("amphitheatre.app/character={name}"));
+    let mut records = api.list(&params).await.map_err(Error::KubeError)?.items;
+
+    sort(&mut records);
+    Ok(records)
+}
+
+/// Sort the build records, the newest first.
+fn sort(records: &mut [BuildRecord]) {
+    records.sort_by_key(|record| Reverse(record.spec.started_at.as_ref().map(|time| time.0)));
+}
+
+/// Returns the records beyond the max number, they are the oldest ones of the sorted records.
+fn outdated(records: Vec<BuildRecord>) -> impl Iterator<Item = BuildRecord> {
+    records.into_iter().skip(MAX_RECORDS)
+}
+
+/// Patch the record with the outcome, and the time it's finished.
+async fn close(
+    api: &Api<BuildRecord>,
+    record: &BuildRecord,
+    outcome: BuildOutcome,
+    digest: Option<String>,
+    message: Option<String>,
+) -> Result<()> {
+    let now = Timestamp::now();
+    let duration = record.spec.started_at.as_ref().map(|started| now.duration_since(started.0).as_secs());
+    let patch = json!({
+        "spec": {
+            "finishedAt": Time(now),
+            "duration": duration,
+            "outcome": outcome,
+            "digest": digest,
+            "message": message,
+        }
+    });
+    api.patch(&record.name_any(), &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+
+    Ok(())
+}
+
+/// Returns the record of a new build of the actor, started at the given time.
+fn new(actor: &Actor, builder: &str, now: Timestamp) -> BuildRecord {
+    let spec = BuildRecordSpec {
+        actor: actor.spec.name.clone(),
+        revision: actor.spec.source.as_ref().filter(|_| !actor.spec.live).map(|source| source.rev()),
+        image: actor.spec.image.clone(),
+        builder: builder.to_string(),
+        started_at: Some(Time(now)),
+        ..Default::default()
+    };
+
+    BuildRecord {
+        metadata: ObjectMeta {
+            // Let the server name it, the builds may start within the same second.
+            generate_name: Some(format!("{}-", actor.spec.name)),
+            owner_references: Some(vec![actor.controller_owner_ref(&()).unwrap()]),
+            labels: Some(BTreeMap::from([
+                ("amphitheatre.app/character".into(), actor.spec.name.clone()),
+                ("app.kubernetes.io/managed-by".into(), "Amphitheatre".into()),
+            ])),
+            ..Default::default()
+        },
+        spec,
+    }
+}
+
+impl BuildRecordSpec {
+    /// Check if it's the record of the same build, from the same source by the same builder.
+    fn same_build(&self, other: &BuildRecordSpec) -> bool {
+        (&self.revision, &self.image, &self.builder) == (&other.revision, &other.image, &other.builder)
+    }
+}
+
+/// Returns the running build record of the actor, if any.
+async fn running(client: &Client, actor: &Actor) -> Result<Option<BuildRecord>> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let records = list(client, &namespace, &actor.spec.name).await?;
+
+    Ok(records.into_iter().find(|record| record.spec.outcome == BuildOutcome::Running))
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_common::resource::ActorSpec;
+    use amp_common::schema::GitReference;
+
+    use super::*;
+
+    fn actor(live: bool) -> Actor {
+        let source = GitReference {
+            repo: "https://github.com/amp/app".into(),
+            rev: Some("abc1234".into()),
+            ..Default::default()
+        };
+        let spec = ActorSpec {
+            name: "app".into(),
+            image: "registry/app:v1".into(),
+            source: Some(source),
+            live,
+            ..Default::default()
+        };
+        let mut actor = Actor::new("app", spec);
+        actor.metadata.uid = Some("9f1f0b4e-4f4c-4d27-8a0b-7c7e0d4c2a11".into());
+        actor
+    }
+
+    fn record(name: &str, started_at: Option<i64>) -> BuildRecord {
+        let started_at = started_at.map(|seconds| Time(Timestamp::from_second(seconds).unwrap()));
+        BuildRecord::new(name, BuildRecordSpec { started_at, ..Default::default() })
+    }
+
+    #[test]
+    fn test_new_record() {
+        let now = Timestamp::from_second(1_700_000_000).unwrap();
+        let actor = actor(false);
+        let record = new(&actor, "kaniko", now);
+
+        assert_eq!(record.metadata.generate_name, Some("app-".into()));
+        assert_eq!(record.metadata.owner_references.unwrap()[0].name, "app");
+        assert_eq!(record.metadata.labels.unwrap().get("amphitheatre.app/character"), Some(&"app".to_string()));
+        assert_eq!(record.spec.actor, "app");
+        assert_eq!(record.spec.revision, Some(actor.spec.source.as_ref().unwrap().rev()));
+        assert_eq!(record.spec.image, "registry/app:v1");
+        assert_eq!(record.spec.builder, "kaniko");
+        assert_eq!(record.spec.started_at, Some(Time(now)));
+        assert_eq!(
+            (record.spec.outcome, record.spec.finished_at, record.spec.duration),
+            (BuildOutcome::Running, None, None)
+        );
+
+        // The live actor is built from the synchronized sources.
+        assert_eq!(new(&actor(true), "kpack", now).spec.revision, None);
+    }
+
+    #[test]
+    fn test_same_build() {
+        let now = Timestamp::from_second(1_700_000_000).unwrap();
+        let record = new(&actor(false), "kaniko", now).spec;
+
+        assert!(record.same_build(&new(&actor(false), "kaniko", Timestamp::now()).spec));
+        assert!(!record.same_build(&new(&actor(false), "lifecycle", now).spec));
+        assert!(!record.same_build(&new(&actor(true), "kaniko", now).spec));
+
+        let mut rebuilt = actor(false);
+        rebuilt.spec.image = "registry/app:v2".into();
+        assert!(!record.same_build(&new(&rebuilt, "kaniko", now).spec));
+    }
+
+    #[test]
+    fn test_prune_oldest_records() {
+        // The records are listed in any order, and the ones without start time are the oldest.
+        let mut records: Vec<BuildRecord> = (0..MAX_RECORDS as i64 + 2)
+            .map(|i| record(&format!("r{i}"), Some(1_700_000_000 + (i * 7) % 23 * 60)))
+            .collect();
+        records.push(record("unknown", None));
+        sort(&mut records);
+
+        let started: Vec<_> = records.iter().map(|record| record.spec.started_at.as_ref().map(|time| time.0)).collect();
+        assert!(started.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        let outdated: Vec<String> = outdated(records.clone()).map(|record| record.name_any()).collect();
+        assert_eq!(outdated.len(), 3);
+        assert_eq!(outdated.last(), Some(&"unknown".to_string()));
+        assert!(records[..MAX_RECORDS].iter().all(|record| !outdated.contains(&record.name_any())));
+    }
+
+    #[test]
+    fn test_keep_records_under_max() {
+        let mut records: Vec<BuildRecord> = (0..3).map(|i| record(&format!("r{i}"), Some(1_700_000_000 + i))).collect();
+        sort(&mut records);
+
+        assert_eq!(records.iter().map(|record| record.name_any()).collect::<Vec<_>>(), vec!["r2", "r1", "r0"]);
+        assert_eq!(outdated(records).count(), 0);
+    }
+}
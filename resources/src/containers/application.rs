@@ -15,6 +15,12 @@
 use amp_common::resource::ActorSpec;
 use k8s_openapi::api::core::v1::Container;
 
+use super::workspace_mount;
+use crate::hot_reload::HotReload;
+
+/// The CNB launcher of the buildpacks image, it sets up the environment of buildpacks before running the command.
+const CNB_LAUNCHER: &str = "/cnb/lifecycle/launcher";
+
 /// Build and return the container spec for the actor
 pub fn container(spec: &ActorSpec) -> Container {
     let mut environments = Some(vec![]);
@@ -36,6 +42,16 @@ pub fn container(spec: &ActorSpec) -> Container {
     }
 }
 
+/// Build and return the container spec for the actor in the hot reload mode,
+/// it runs the dev command in the workspace mounted from the PVC of the live actor.
+pub fn dev_container(spec: &ActorSpec, config: &HotReload) -> Container {
+    Container {
+        command: Some(vec![CNB_LAUNCHER.into(), config.command.clone()]),
+        volume_mounts: Some(vec![workspace_mount()]),
+        ..container(spec)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,4 +66,16 @@ mod tests {
         assert_eq!(container.image, Some("test".into()));
         assert_eq!(container.image_pull_policy, Some("Always".into()));
     }
+
+    #[test]
+    fn test_dev_container() {
+        let spec = ActorSpec { name: "test".into(), image: "test".into(), live: true, ..Default::default() };
+        let config = HotReload { command: "npm run dev".into(), signal: None };
+
+        let container = dev_container(&spec, &config);
+
+        assert_eq!(container.name, "test");
+        assert_eq!(container.command, Some(vec![CNB_LAUNCHER.into(), "npm run dev".into()]));
+        assert_eq!(container.volume_mounts.unwrap()[0].mount_path, "/workspace");
+    }
 }
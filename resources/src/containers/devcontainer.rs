@@ -12,10 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use amp_common::resource::ActorSpec;
-use k8s_openapi::api::core::v1::Container;
+use std::collections::BTreeMap;
 
-use super::workspace_mount;
+use amp_common::resource::{Actor, ActorSpec};
+use k8s_openapi::api::core::v1::{
+    Container, ContainerPort, EnvVar, ExecAction, Lifecycle, LifecycleHandler, Volume, VolumeMount,
+};
+use kube::ResourceExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use super::WORKSPACE_DIR;
+use crate::error::{Error, Result};
 
 /// This is the "universal" image that is used by default if no custom
 /// Dockerfile or image is specified. Ubuntu-based default, large, and
@@ -27,36 +36,429 @@ use super::workspace_mount;
 /// repository.
 const DEFAULT_DEVCONTAINER_IMAGE: &str = "mcr.microsoft.com/devcontainers/universal:linux";
 
-/// Build and return the container spec for the devcontainer.
-pub fn container(_spec: &ActorSpec) -> Container {
+/// The deploy environment key to run the live actor in the development mode, the value is
+/// the path of `devcontainer.json` in the character repository, or `true` for the default path.
+pub const DEVCONTAINER_KEY: &str = "AMP_DEVCONTAINER";
+
+/// The default path of `devcontainer.json` in the character repository.
+pub const DEFAULT_DEVCONTAINER_PATH: &str = ".devcontainer/devcontainer.json";
+
+/// The annotation of the actor to keep its loaded `devcontainer.json`.
+pub const DEVCONTAINER_ANNOTATION: &str = "amphitheatre.app/devcontainer";
+
+/// The supported subset of `devcontainer.json`,
+/// see [the specification](https://containers.dev/implementors/json_reference/).
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevContainer {
+    pub image: Option<String>,
+    pub build: Option<DevContainerBuild>,
+    #[serde(default)]
+    pub features: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub forward_ports: Vec<Port>,
+    pub post_create_command: Option<Command>,
+    #[serde(default)]
+    pub container_env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub mounts: Vec<Mount>,
+    pub workspace_folder: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DevContainerBuild {
+    pub dockerfile: Option<String>,
+    pub context: Option<String>,
+}
+
+/// The forwarded port, a number or `host:port`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Port {
+    Number(u16),
+    Host(String),
+}
+
+/// The lifecycle command, in the shell form, the exec form, or the named commands run in parallel.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Command {
+    Shell(String),
+    Exec(Vec<String>),
+    Parallel(BTreeMap<String, Command>),
+}
+
+/// The mount, in the docker `--mount` form or an object.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Mount {
+    Line(String),
+    Object {
+        source: Option<String>,
+        target: String,
+        #[serde(rename = "type")]
+        kind: Option<String>,
+    },
+}
+
+impl DevContainer {
+    /// Parse the `devcontainer.json`, which is JSON with comments and trailing commas.
+    pub fn parse(data: &str) -> Result<Self> {
+        serde_json::from_str(&strip_jsonc(data)).map_err(Error::SerializationError)
+    }
+
+    /// Returns the loaded `devcontainer.json` of the actor in the development mode.
+    pub fn from_actor(actor: &Actor) -> Option<Self> {
+        let data = actor.annotations().get(DEVCONTAINER_ANNOTATION)?;
+        serde_json::from_str(data).inspect_err(|err| warn!("Invalid devcontainer annotation: {}", err)).ok()
+    }
+
+    /// Returns the reason if the devcontainer is configured with what's not supported yet,
+    /// it's not run at all instead of running another image without them.
+    pub fn unsupported(&self) -> Option<String> {
+        if self.image.is_none() && self.build.is_some() {
+            return Some("Building the devcontainer from a Dockerfile is not supported, use a prebuilt image".into());
+        }
+        if !self.features.is_empty() {
+            let features: Vec<&str> = self.features.keys().map(String::as_str).collect();
+            return Some(format!(
+                "The devcontainer features {} are not supported, install them into the image",
+                features.join(", ")
+            ));
+        }
+
+        None
+    }
+
+    /// Returns the ports of the container named `name`. The `host:port` of another host is a port
+    /// of another service, e.g. `db:5432` of a Docker Compose service, which is not supported.
+    fn ports(&self, name: &str) -> Vec<i32> {
+        self.forward_ports
+            .iter()
+            .filter_map(|port| match port {
+                Port::Number(port) => Some(*port as i32),
+                Port::Host(value) => match value.rsplit_once(':') {
+                    Some((host, port)) if ["localhost", "127.0.0.1", "0.0.0.0", name].contains(&host) => {
+                        port.parse().ok()
+                    }
+                    _ => {
+                        warn!(
+                            "The forwarded port {} of another service is not supported, expose it by the service",
+                            value
+                        );
+                        None
+                    }
+                },
+            })
+            .collect()
+    }
+
+    /// Returns the named volumes to mount, the bind mounts are not supported in the cluster.
+    fn volumes(&self) -> Vec<(String, String)> {
+        self.mounts
+            .iter()
+            .filter_map(|mount| {
+                let (source, target, kind) = match mount {
+                    Mount::Line(line) => {
+                        let options: BTreeMap<&str, &str> =
+                            line.split(',').filter_map(|option| option.split_once('=')).collect();
+                        let source = options.get("source").or(options.get("src")).map(|s| s.to_string());
+                        let target = options.get("target").or(options.get("dst")).or(options.get("destination"));
+                        (source, target?.to_string(), options.get("type").map(|s| s.to_string()))
+                    }
+                    Mount::Object { source, target, kind } => (source.clone(), target.clone(), kind.clone()),
+                };
+                if kind.as_deref().unwrap_or("volume") != "volume" {
+                    warn!("The {:?} mount to {} is not supported, only volumes are", kind, target);
+                    return None;
+                }
+                Some((source.unwrap_or_else(|| target.trim_matches('/').replace('/', "-")), target))
+            })
+            .collect()
+    }
+}
+
+impl Command {
+    /// Returns the command in the exec form, the parallel commands are run in the background and waited.
+    fn args(&self) -> Vec<String> {
+        match self {
+            Command::Shell(line) => vec!["/bin/sh".into(), "-c".into(), line.clone()],
+            Command::Exec(args) => args.clone(),
+            Command::Parallel(commands) => {
+                let lines: Vec<String> = commands.values().map(|command| shell_words(&command.args())).collect();
+                vec!["/bin/sh".into(), "-c".into(), format!("{} & wait", lines.join(" & "))]
+            }
+        }
+    }
+}
+
+/// Returns the enabled path of `devcontainer.json` of the live actor, if any.
+pub fn path(spec: &ActorSpec) -> Option<String> {
+    if !spec.live {
+        return None;
+    }
+    let env = spec.character.deploy.as_ref()?.env.as_ref()?;
+
+    match env.get(DEVCONTAINER_KEY)?.trim() {
+        "" | "false" | "0" => None,
+        "true" | "1" => Some(DEFAULT_DEVCONTAINER_PATH.to_string()),
+        path => Some(path.trim_start_matches("./").to_string()),
+    }
+}
+
+/// Build and return the container spec for the devcontainer of the actor,
+/// it runs in the actor's pod with the synchronized workspace mounted.
+pub fn container(spec: &ActorSpec, config: &DevContainer) -> Container {
+    // The unsupported Dockerfile and features are rejected before deploying, see `DevContainer::unsupported`.
+    let image = config.image.clone().unwrap_or_else(|| DEFAULT_DEVCONTAINER_IMAGE.to_string());
+
+    let workspace = config.workspace_folder.clone().unwrap_or_else(|| WORKSPACE_DIR.to_string());
+    let mut volume_mounts =
+        vec![VolumeMount { name: "workspace".into(), mount_path: workspace.clone(), ..Default::default() }];
+    volume_mounts.extend(config.volumes().into_iter().map(|(source, target)| VolumeMount {
+        name: volume_name(&source),
+        mount_path: target,
+        ..Default::default()
+    }));
+
     Container {
-        name: "builder".to_string(),
-
-        // TODO: devcontainer image should be detected from actor spec,
-        // it's parsed from the devcontainer.json file.
-        // For now, we use the default image for testing.
-        image: Some(DEFAULT_DEVCONTAINER_IMAGE.to_string()),
-        // Use "command: ['sleep', 'infinity']" to keep the container running indefinitely.
-        // Helpful for maintaining pod activity when no specific application logic is needed.
-        // Not recommended for production; production containers should run actual applications.
+        name: spec.name.clone(),
+        image: Some(image),
+        // Use "command: ['sleep', 'infinity']" to keep the container running indefinitely,
+        // just like the `overrideCommand` of devcontainer, the development happens inside.
         command: Some(vec!["sleep".into(), "infinity".into()]),
         image_pull_policy: Some("IfNotPresent".to_string()),
-        volume_mounts: Some(vec![workspace_mount()]),
+        env: Some(config.container_env.iter().map(|(name, value)| env(name, value)).collect()),
+        ports: Some(
+            config
+                .ports(&spec.name)
+                .into_iter()
+                .map(|port| ContainerPort { container_port: port, ..Default::default() })
+                .collect(),
+        ),
+        lifecycle: config.post_create_command.as_ref().map(|command| Lifecycle {
+            post_start: Some(LifecycleHandler {
+                exec: Some(ExecAction { command: Some(command.args()) }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        working_dir: Some(workspace),
+        volume_mounts: Some(volume_mounts),
         ..Default::default()
     }
 }
 
+/// Returns the volumes of the named mounts of devcontainer.
+pub fn volumes(config: &DevContainer) -> Vec<Volume> {
+    config
+        .volumes()
+        .into_iter()
+        .map(|(source, _)| Volume {
+            name: volume_name(&source),
+            empty_dir: Some(Default::default()),
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[inline]
+fn volume_name(source: &str) -> String {
+    let name: String = source.to_lowercase().chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '-' }).collect();
+    format!("devcontainer-{}", name.trim_matches('-'))
+}
+
+#[inline]
+fn env(name: &str, value: &str) -> EnvVar {
+    EnvVar { name: name.to_string(), value: Some(value.to_string()), ..Default::default() }
+}
+
+/// Join the arguments into a shell command line, quote them if necessary.
+fn shell_words(args: &[String]) -> String {
+    let quote = |arg: &String| {
+        if !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:".contains(c)) {
+            arg.clone()
+        } else {
+            format!("'{}'", arg.replace('\'', r"'\''"))
+        }
+    };
+    args.iter().map(quote).collect::<Vec<_>>().join(" ")
+}
+
+/// Strip the comments and trailing commas of JSON with comments.
+fn strip_jsonc(data: &str) -> String {
+    let mut output = String::with_capacity(data.len());
+    let mut chars = data.chars().peekable();
+    let (mut in_string, mut escaped) = (false, false);
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                output.push(c);
+            }
+            ('/', Some('/')) => while chars.next_if(|c| *c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            (']' | '}', _) => {
+                // Drop the trailing comma before the closing bracket.
+                let trimmed = output.trim_end().len();
+                if output[..trimmed].ends_with(',') {
+                    output.truncate(trimmed - 1);
+                }
+                output.push(c);
+            }
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use amp_common::resource::CharacterSpec;
+    use amp_common::schema::Deploy;
+
     use super::*;
 
+    const DEVCONTAINER: &str = r#"{
+        // The image of the devcontainer.
+        "image": "mcr.microsoft.com/devcontainers/rust:1", /* inline */
+        "forwardPorts": [3000, "db:5432"],
+        "postCreateCommand": "cargo build // not a comment",
+        "containerEnv": { "RUST_LOG": "debug" },
+        "mounts": [
+            "source=cargo-cache,target=/usr/local/cargo,type=volume",
+            { "source": "${localEnv:HOME}", "target": "/home", "type": "bind" },
+        ],
+    }"#;
+
+    #[test]
+    fn test_parse_devcontainer() {
+        let config = DevContainer::parse(DEVCONTAINER).unwrap();
+
+        assert_eq!(config.image, Some("mcr.microsoft.com/devcontainers/rust:1".into()));
+        assert_eq!(config.forward_ports, vec![Port::Number(3000), Port::Host("db:5432".into())]);
+        assert_eq!(config.post_create_command, Some(Command::Shell("cargo build // not a comment".into())));
+        assert_eq!(config.container_env.get("RUST_LOG"), Some(&"debug".to_string()));
+        assert_eq!(config.mounts.len(), 2);
+    }
+
     #[test]
     fn test_devcontainer() {
-        let actor = ActorSpec { name: "test".into(), image: "test".into(), ..Default::default() };
-        let container = container(&actor);
+        let spec = ActorSpec { name: "test".into(), image: "test".into(), ..Default::default() };
+        let config = DevContainer::parse(DEVCONTAINER).unwrap();
+        let container = container(&spec, &config);
 
-        assert_eq!(container.name, "builder");
-        assert_eq!(container.image, Some(DEFAULT_DEVCONTAINER_IMAGE.to_string()));
+        assert_eq!(container.name, "test");
+        assert_eq!(container.image, Some("mcr.microsoft.com/devcontainers/rust:1".into()));
         assert_eq!(container.image_pull_policy, Some("IfNotPresent".to_string()));
+        assert_eq!(container.working_dir, Some(WORKSPACE_DIR.into()));
+
+        let ports: Vec<i32> = container.ports.unwrap().iter().map(|port| port.container_port).collect();
+        assert_eq!(ports, vec![3000]);
+
+        let command = container.lifecycle.unwrap().post_start.unwrap().exec.unwrap().command.unwrap();
+        assert_eq!(command, vec!["/bin/sh", "-c", "cargo build // not a comment"]);
+
+        let mounts = container.volume_mounts.unwrap();
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(
+            (mounts[1].name.as_str(), mounts[1].mount_path.as_str()),
+            ("devcontainer-cargo-cache", "/usr/local/cargo")
+        );
+        assert_eq!(volumes(&config).len(), 1);
+    }
+
+    #[test]
+    fn test_devcontainer_ports() {
+        let ports = ["localhost:8080", "127.0.0.1:8081", "test:8082", "db:5432", "test:http", "8083"];
+        let mut config = DevContainer {
+            forward_ports: ports.iter().map(|port| Port::Host(port.to_string())).collect(),
+            ..Default::default()
+        };
+        config.forward_ports.push(Port::Number(3000));
+
+        assert_eq!(config.ports("test"), vec![8080, 8081, 8082, 3000]);
+    }
+
+    #[test]
+    fn test_devcontainer_default_image() {
+        let spec = ActorSpec { name: "test".into(), image: "test".into(), ..Default::default() };
+        assert_eq!(container(&spec, &DevContainer::default()).image, Some(DEFAULT_DEVCONTAINER_IMAGE.to_string()));
+    }
+
+    #[test]
+    fn test_devcontainer_unsupported() {
+        let config = DevContainer::parse(DEVCONTAINER).unwrap();
+        assert_eq!(config.unsupported(), None);
+
+        let build = Some(DevContainerBuild { dockerfile: Some("Dockerfile".into()), context: Some("..".into()) });
+        let config = DevContainer { build: build.clone(), ..Default::default() };
+        assert!(config.unsupported().unwrap().contains("Dockerfile"));
+
+        // The prebuilt image takes precedence over the Dockerfile.
+        let config = DevContainer { image: Some("rust:1".into()), build, ..Default::default() };
+        assert_eq!(config.unsupported(), None);
+
+        let config = DevContainer::parse(r#"{ "features": { "ghcr.io/devcontainers/features/node:1": {} } }"#);
+        let message = config.unwrap().unsupported().unwrap();
+        assert!(message.contains("ghcr.io/devcontainers/features/node:1"));
+    }
+
+    #[test]
+    fn test_parallel_command() {
+        let command = Command::Parallel(BTreeMap::from([
+            ("npm".to_string(), Command::Shell("npm install".into())),
+            ("pip".to_string(), Command::Exec(vec!["pip".into(), "install".into(), "-r".into(), "a b.txt".into()])),
+        ]));
+
+        assert_eq!(command.args()[2], "/bin/sh -c 'npm install' & pip install -r 'a b.txt' & wait");
+    }
+
+    #[test]
+    fn test_devcontainer_path() {
+        let spec = |value: &str| ActorSpec {
+            live: true,
+            character: CharacterSpec {
+                deploy: Some(Deploy {
+                    env: Some(HashMap::from([(DEVCONTAINER_KEY.to_string(), value.to_string())])),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(path(&spec("true")), Some(DEFAULT_DEVCONTAINER_PATH.into()));
+        assert_eq!(
+            path(&spec("./.devcontainer/rust/devcontainer.json")),
+            Some(".devcontainer/rust/devcontainer.json".into())
+        );
+        assert_eq!(path(&spec("false")), None);
+        assert_eq!(path(&ActorSpec { live: false, ..spec("true") }), None);
     }
 }
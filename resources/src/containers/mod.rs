@@ -19,7 +19,9 @@ pub mod kaniko;
 pub mod lifecycle;
 pub mod syncer;
 
-use k8s_openapi::api::core::v1::{KeyToPath, SecretVolumeSource, Volume, VolumeMount};
+use k8s_openapi::api::core::v1::{
+    KeyToPath, PersistentVolumeClaimVolumeSource, SecretVolumeSource, Volume, VolumeMount,
+};
 
 const WORKSPACE_DIR: &str = "/workspace";
 
@@ -29,6 +31,16 @@ pub fn workspace_volume() -> Volume {
     Volume { name: "workspace".to_string(), empty_dir: Some(Default::default()), ..Default::default() }
 }
 
+/// volume for /workspace based on the PersistentVolumeClaim of live actor
+#[inline]
+pub fn workspace_claim_volume(claim_name: String) -> Volume {
+    Volume {
+        name: "workspace".to_string(),
+        persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource { claim_name, read_only: Some(false) }),
+        ..Default::default()
+    }
+}
+
 /// volume mount for /workspace
 #[inline]
 pub fn workspace_mount() -> VolumeMount {
@@ -65,6 +77,14 @@ mod tests {
         assert_eq!(volume.empty_dir, Some(Default::default()));
     }
 
+    #[test]
+    fn test_workspace_claim_volume() {
+        let volume = workspace_claim_volume("amp-example-go-pvc".into());
+
+        assert_eq!(volume.name, "workspace");
+        assert_eq!(volume.persistent_volume_claim.unwrap().claim_name, "amp-example-go-pvc");
+    }
+
     #[test]
     fn test_workspace_mount() {
         let mount = workspace_mount();
@@ -66,7 +66,7 @@ pub fn container(actor: &Actor, security_context: &Option<SecurityContext>) -> R
 
 /// Get the playbook name from the owner reference.
 #[inline]
-fn owner_reference(actor: &Actor) -> Result<String> {
+pub fn owner_reference(actor: &Actor) -> Result<String> {
     actor
         .owner_references()
         .iter()
@@ -74,6 +74,11 @@ fn owner_reference(actor: &Actor) -> Result<String> {
         .ok_or_else(|| Error::MissingObjectKey(".metadata.ownerReferences"))
 }
 
+/// Returns the name of the durable NATS consumer of the actor's syncer, it's created by the syncer itself.
+pub fn consumer_name(actor: &str) -> String {
+    format!("amp-syncer-{actor}")
+}
+
 #[cfg(test)]
 mod tests {
     use amp_common::resource::ActorSpec;
@@ -15,17 +15,26 @@
 use std::collections::BTreeMap;
 
 use amp_common::resource::Actor;
+use jiff::Timestamp;
 use k8s_openapi::api::apps::v1::{Deployment, DeploymentSpec};
 use k8s_openapi::api::core::v1::{PodSpec, PodTemplateSpec};
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
-use kube::api::{Patch, PatchParams, PostParams};
+use kube::api::{ListParams, Patch, PatchParams, PostParams};
 use kube::core::ObjectMeta;
 use kube::{Api, Client, Resource, ResourceExt};
+use serde_json::json;
 use tracing::{debug, info};
 
+use super::actor;
 use super::error::{Error, Result};
 use super::{hash, LAST_APPLIED_HASH_KEY};
 
+/// The annotation key to remember the replicas of a suspended Deployment.
+const SUSPENDED_REPLICAS_KEY: &str = "amphitheatre.app/suspended-replicas";
+
+/// The pod template annotation key to trigger a rolling restart of the Deployment.
+const RESTARTED_AT_KEY: &str = "kubectl.kubernetes.io/restartedAt";
+
 pub async fn exists(client: &Client, namespace: &str, name: &str) -> Result<bool> {
     let api: Api<Deployment> = Api::namespaced(client.clone(), namespace);
     Ok(api.get_opt(name).await.map_err(Error::KubeError)?.is_some())
@@ -66,6 +75,102 @@ pub async fn update(
     Ok(deployment)
 }
 
+/// List all the Deployments managed by Amphitheatre in the namespace
+pub async fn list(client: &Client, namespace: &str) -> Result<Vec<Deployment>> {
+    let api: Api<Deployment> = Api::namespaced(client.clone(), namespace);
+    let params = ListParams::default().labels("app.kubernetes.io/managed-by=Amphitheatre");
+    let deployments = api.list(&params).await.map_err(Error::KubeError)?;
+
+    Ok(deployments.items)
+}
+
+/// Scale the Deployment down to zero, and remember its replicas for resuming later.
+pub async fn suspend(client: &Client, namespace: &str, deployment: &Deployment) -> Result<()> {
+    let api: Api<Deployment> = Api::namespaced(client.clone(), namespace);
+    let name = deployment.name_any();
+
+    let replicas = deployment.spec.as_ref().and_then(|spec| spec.replicas).unwrap_or(1);
+    if replicas == 0 {
+        debug!("The Deployment {} is already suspended", name);
+        return Ok(());
+    }
+
+    let patch = json!({
+        "metadata": { "annotations": { SUSPENDED_REPLICAS_KEY: replicas.to_string() } },
+        "spec": { "replicas": 0 },
+    });
+    api.patch(&name, &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+
+    info!("Suspended Deployment {} with {} replicas", name, replicas);
+    Ok(())
+}
+
+/// Returns the new Deployment created as suspended, with zero replicas, it's resumed with one replica.
+pub fn suspended(mut deployment: Deployment) -> Deployment {
+    deployment.annotations_mut().insert(SUSPENDED_REPLICAS_KEY.into(), "1".into());
+    if let Some(spec) = deployment.spec.as_mut() {
+        spec.replicas = Some(0);
+    }
+
+    deployment
+}
+
+/// Restore the replicas of a suspended Deployment.
+pub async fn resume(client: &Client, namespace: &str, deployment: &Deployment) -> Result<()> {
+    let api: Api<Deployment> = Api::namespaced(client.clone(), namespace);
+    let name = deployment.name_any();
+
+    let Some(replicas) = deployment.annotations().get(SUSPENDED_REPLICAS_KEY) else {
+        debug!("The Deployment {} is not suspended", name);
+        return Ok(());
+    };
+    let replicas: i32 = replicas.parse().unwrap_or(1);
+
+    let patch = json!({
+        "metadata": { "annotations": { SUSPENDED_REPLICAS_KEY: null } },
+        "spec": { "replicas": replicas },
+    });
+    api.patch(&name, &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+
+    info!("Resumed Deployment {} with {} replicas", name, replicas);
+    Ok(())
+}
+
+/// Restart the Deployment with a rolling update, just like `kubectl rollout restart`.
+pub async fn restart(client: &Client, namespace: &str, name: &str) -> Result<()> {
+    let api: Api<Deployment> = Api::namespaced(client.clone(), namespace);
+
+    let patch = json!({
+        "spec": { "template": { "metadata": { "annotations": {
+            RESTARTED_AT_KEY: Timestamp::now().to_string()
+        }}}},
+    });
+    api.patch(name, &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+
+    info!("Restarted Deployment {}", name);
+    Ok(())
+}
+
+/// Scale the replicas of the Deployment.
+pub async fn scale(client: &Client, namespace: &str, name: &str, replicas: i32) -> Result<()> {
+    let api: Api<Deployment> = Api::namespaced(client.clone(), namespace);
+
+    let patch = json!({ "spec": { "replicas": replicas } });
+    api.patch(name, &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+
+    info!("Scaled Deployment {} to {} replicas", name, replicas);
+    Ok(())
+}
+
+/// Returns the hash of what's deployed for the actor, that is the spec and the deployed image,
+/// so that the Deployment is updated once the actor is rolled back or rebuilt with a new digest.
+pub fn applied_hash(actor: &Actor) -> Result<String> {
+    match actor::deployed_image(actor) {
+        image if image == actor.spec.image => hash(&actor.spec),
+        image => hash(&(&actor.spec, image)),
+    }
+}
+
 pub fn new(actor: &Actor, pod: PodSpec) -> Result<Deployment> {
     let name = actor.name_any();
 
@@ -75,7 +180,7 @@ pub fn new(actor: &Actor, pod: PodSpec) -> Result<Deployment> {
         ("amphitheatre.app/character".into(), name.clone()),
         ("app.kubernetes.io/managed-by".into(), "Amphitheatre".into()),
     ]);
-    let annotations = BTreeMap::from([(LAST_APPLIED_HASH_KEY.into(), hash(&actor.spec)?)]);
+    let annotations = BTreeMap::from([(LAST_APPLIED_HASH_KEY.into(), applied_hash(actor)?)]);
     let metadata = ObjectMeta {
         name: Some(name),
         owner_references: Some(vec![owner_reference]),
@@ -97,3 +202,38 @@ pub fn new(actor: &Actor, pod: PodSpec) -> Result<Deployment> {
     // Build and return the deployment resource
     Ok(Deployment { metadata, spec: Some(spec), ..Default::default() })
 }
+
+#[cfg(test)]
+mod tests {
+    use amp_common::resource::ActorSpec;
+
+    use super::*;
+
+    #[test]
+    fn test_suspended() {
+        let mut actor = Actor::new("test", ActorSpec::default());
+        actor.metadata.uid = Some("9f1f0b4e-4f4c-4d27-8a0b-7c7e0d4c2a11".into());
+        let deployment = suspended(new(&actor, PodSpec::default()).unwrap());
+
+        assert_eq!(deployment.spec.unwrap().replicas, Some(0));
+        assert_eq!(deployment.metadata.annotations.unwrap().get(SUSPENDED_REPLICAS_KEY), Some(&"1".to_string()));
+    }
+
+    #[test]
+    fn test_applied_hash() {
+        let mut actor = Actor::new("test", ActorSpec { image: "registry/app:v1".into(), ..ActorSpec::default() });
+        let built = applied_hash(&actor).unwrap();
+        assert_eq!(built, hash(&actor.spec).unwrap());
+
+        // The rebuild of the same tag is deployed by its new digest.
+        actor.annotations_mut().insert(actor::BUILT_IMAGE_ANNOTATION.into(), "registry/app:v1@sha256:1111".into());
+        let first = applied_hash(&actor).unwrap();
+        actor.annotations_mut().insert(actor::BUILT_IMAGE_ANNOTATION.into(), "registry/app:v1@sha256:2222".into());
+        let rebuilt = applied_hash(&actor).unwrap();
+        assert_ne!(first, built);
+        assert_ne!(rebuilt, first);
+
+        actor.annotations_mut().insert(actor::ROLLBACK_IMAGE_ANNOTATION.into(), "registry/app:v0".into());
+        assert_ne!(applied_hash(&actor).unwrap(), rebuilt);
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use amp_common::resource::{Actor, ActorSpec};
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{AttachParams, ListParams};
+use kube::{Api, Client, ResourceExt};
+use tracing::{info, warn};
+
+use crate::deployment;
+use crate::error::{Error, Result};
+
+/// The deploy environment key of the command to run the live actor from the
+/// synchronized workspace, it enables the hot reload mode, e.g. `npm run dev`.
+pub const DEV_COMMAND_KEY: &str = "AMP_DEV_COMMAND";
+
+/// The deploy environment key of the signal sent to the dev command after
+/// synchronizing, e.g. `HUP`, the application is restarted if it's not set.
+pub const RELOAD_SIGNAL_KEY: &str = "AMP_RELOAD_SIGNAL";
+
+/// The hot reload mode of a live actor, the application runs the dev command
+/// in the workspace directly, instead of being rebuilt for every change.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HotReload {
+    pub command: String,
+    /// The signal name without the `SIG` prefix, None to restart the application.
+    pub signal: Option<String>,
+}
+
+impl HotReload {
+    /// Returns the hot reload mode declared by the deploy config of the live actor, if any.
+    pub fn from_spec(spec: &ActorSpec) -> Option<Self> {
+        if !spec.live {
+            return None;
+        }
+        let env = spec.character.deploy.as_ref()?.env.as_ref()?;
+        let command = env.get(DEV_COMMAND_KEY).filter(|command| !command.trim().is_empty())?;
+
+        let signal = env.get(RELOAD_SIGNAL_KEY).and_then(|value| {
+            let name = value.trim().to_uppercase();
+            let name = name.strip_prefix("SIG").unwrap_or(&name);
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Some(name.to_string());
+            }
+            warn!("Invalid reload signal {}, fallback to restart", value);
+            None
+        });
+
+        Some(HotReload { command: command.clone(), signal })
+    }
+}
+
+/// Reload the application of the actor after its workspace is synchronized,
+/// send the signal to the dev command if configured, otherwise restart the Deployment.
+pub async fn reload(client: &Client, actor: &Actor, config: &HotReload) -> Result<()> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let name = actor.name_any();
+
+    let Some(signal) = &config.signal else {
+        return deployment::restart(client, &namespace, &name).await;
+    };
+
+    // The dev command is the main process of the application container, see `containers::application`.
+    let api: Api<Pod> = Api::namespaced(client.clone(), &namespace);
+    let params = ListParams::default().labels(&format!("amphitheatre.app/character={name}"));
+    let command = vec!["/bin/sh".to_string(), "-c".to_string(), format!("kill -s {signal} 1")];
+    for pod in api.list(&params).await.map_err(Error::KubeError)? {
+        let running = pod.status.as_ref().and_then(|status| status.phase.as_deref()) == Some("Running");
+        let application = pod.spec.as_ref().is_some_and(|spec| spec.containers.iter().any(|c| c.name == name));
+        if !running || !application {
+            continue;
+        }
+
+        let attach = AttachParams::default().container(&name).stdout(false).stderr(false);
+        let mut process = api.exec(&pod.name_any(), command.clone(), &attach).await.map_err(Error::KubeError)?;
+        let status = match process.take_status() {
+            Some(status) => status.await,
+            None => None,
+        };
+        if status.as_ref().and_then(|status| status.status.as_deref()) != Some("Success") {
+            warn!("Failed to send {} to Pod {}: {:?}", signal, pod.name_any(), status.and_then(|s| s.message));
+            continue;
+        }
+        info!("Sent {} to the application of Pod {}", signal, pod.name_any());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use amp_common::resource::CharacterSpec;
+    use amp_common::schema::Deploy;
+
+    use super::*;
+
+    fn spec(env: &[(&str, &str)]) -> ActorSpec {
+        let env = env.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect::<HashMap<_, _>>();
+        ActorSpec {
+            live: true,
+            character: CharacterSpec {
+                deploy: Some(Deploy { env: Some(env), ..Default::default() }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hot_reload_from_spec() {
+        let config = HotReload::from_spec(&spec(&[(DEV_COMMAND_KEY, "npm run dev"), (RELOAD_SIGNAL_KEY, "sighup")]));
+        assert_eq!(config, Some(HotReload { command: "npm run dev".into(), signal: Some("HUP".into()) }));
+
+        let config = HotReload::from_spec(&spec(&[(DEV_COMMAND_KEY, "npm run dev"), (RELOAD_SIGNAL_KEY, "HUP; rm")]));
+        assert_eq!(config.unwrap().signal, None);
+
+        assert_eq!(HotReload::from_spec(&spec(&[(RELOAD_SIGNAL_KEY, "HUP")])), None);
+        assert_eq!(HotReload::from_spec(&ActorSpec { live: false, ..spec(&[(DEV_COMMAND_KEY, "npm start")]) }), None);
+    }
+}
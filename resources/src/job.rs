@@ -17,7 +17,7 @@ use std::collections::BTreeMap;
 use amp_common::resource::Actor;
 use k8s_openapi::api::batch::v1::{Job, JobSpec};
 use k8s_openapi::api::core::v1::{PodSpec, PodTemplateSpec};
-use kube::api::{Patch, PatchParams, PostParams};
+use kube::api::{DeleteParams, Patch, PatchParams, PostParams};
 use kube::core::ObjectMeta;
 use kube::{Api, Client, Resource, ResourceExt};
 
@@ -69,6 +69,21 @@ pub async fn update(client: &Client, actor: &Actor, pod: PodSpec) -> Result<Job>
     Ok(job)
 }
 
+/// Delete the build Job and its pods, it's fine if the Job does not exist.
+pub async fn delete(client: &Client, actor: &Actor) -> Result<()> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<Job> = Api::namespaced(client.clone(), namespace.as_str());
+    let name = format!("{}-builder", actor.spec.name);
+
+    match api.delete(&name, &DeleteParams::background()).await {
+        Ok(_) => tracing::info!("Deleted Job: {}", name),
+        Err(kube::Error::Api(err)) if err.code == 404 => tracing::debug!("Not found Job {}", name),
+        Err(err) => return Err(Error::KubeError(err)),
+    }
+
+    Ok(())
+}
+
 /// Create a Job for build images
 fn new(actor: &Actor, pod: PodSpec) -> Result<Job> {
     let name = format!("{}-builder", actor.spec.name);
@@ -114,3 +129,28 @@ pub async fn completed(client: &Client, actor: &Actor) -> Result<bool> {
         Ok(false)
     }
 }
+
+/// Returns the reason if the build Job failed, or None if it's running or succeeded.
+pub async fn failed(client: &Client, actor: &Actor) -> Result<Option<String>> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<Job> = Api::namespaced(client.clone(), namespace.as_str());
+    let name = format!("{}-builder", actor.spec.name);
+
+    let Some(status) = api.get_opt(&name).await.map_err(Error::KubeError)?.and_then(|job| job.status) else {
+        return Ok(None);
+    };
+
+    // The Job has a `Failed` condition once its backoff limit is reached.
+    let condition =
+        status.conditions.unwrap_or_default().into_iter().find(|c| c.type_ == "Failed" && c.status == "True");
+    if let Some(condition) = condition {
+        let detail: Vec<String> = [condition.reason, condition.message].into_iter().flatten().collect();
+        return Ok(Some(format!("The build Job {name} failed: {}", detail.join(": "))));
+    }
+
+    if status.failed >= Some(1) {
+        return Ok(Some(format!("The build Job {name} failed")));
+    }
+
+    Ok(None)
+}
@@ -104,6 +104,16 @@ pub async fn ready(client: &Client, image: &str) -> Result<bool> {
     Ok(false)
 }
 
+/// List the names of all ClusterBuildpacks managed by Amphitheatre.
+pub async fn list(client: &Client) -> Result<Vec<String>> {
+    super::list_managed(client, &api_resource()).await
+}
+
+/// Delete the ClusterBuildpack by name, unless it's not managed by Amphitheatre.
+pub async fn delete(client: &Client, name: &str) -> Result<()> {
+    super::delete_managed(client, &api_resource(), name).await
+}
+
 #[inline]
 fn api_resource() -> ApiResource {
     ApiResource::from_gvk(&GroupVersionKind::gvk("kpack.io", "v1alpha2", "ClusterBuildpack"))
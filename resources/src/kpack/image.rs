@@ -14,16 +14,19 @@
 
 use amp_common::resource::Actor;
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::Condition;
-use kube::api::{Patch, PatchParams, PostParams};
+use kube::api::{DeleteParams, Patch, PatchParams, PostParams};
 use kube::core::{DynamicObject, GroupVersionKind};
 use kube::discovery::ApiResource;
 use kube::{Api, Client, Resource, ResourceExt};
-use serde_json::{from_value, json};
+use serde_json::{from_value, json, Value};
 use tracing::{debug, info};
 
 use crate::error::{Error, Result};
 use crate::kpack::BuildExt;
 
+/// The annotation of kpack to request a new build of the Image.
+const BUILD_NEEDED_ANNOTATION: &str = "image.kpack.io/buildNeeded";
+
 pub async fn exists(client: &Client, actor: &Actor) -> Result<bool> {
     let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
     let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace.as_str(), &api_resource());
@@ -64,11 +67,61 @@ pub async fn update(client: &Client, actor: &Actor) -> Result<DynamicObject> {
     Ok(image)
 }
 
+/// Delete the Image and its builds, it's fine if the Image does not exist.
+pub async fn delete(client: &Client, actor: &Actor) -> Result<()> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace.as_str(), &api_resource());
+    let name = format!("{}-builder", actor.spec.name);
+
+    match api.delete(&name, &DeleteParams::background()).await {
+        Ok(_) => info!("Deleted Image: {}", name),
+        Err(kube::Error::Api(err)) if err.code == 404 => debug!("Not found Image {}", name),
+        Err(err) => return Err(Error::KubeError(err)),
+    }
+
+    Ok(())
+}
+
+/// Trigger a new build of the Image by annotating its latest Build, just like `kp image trigger`.
+/// Returns false if the Image has not been built yet, it will be built anyway.
+pub async fn trigger(client: &Client, actor: &Actor) -> Result<bool> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace.as_str(), &api_resource());
+    let name = format!("{}-builder", actor.spec.name);
+
+    let image = api.get_opt(&name).await.map_err(Error::KubeError)?;
+    let Some(latest) = image.as_ref().and_then(|image| image.data.pointer("/status/latestBuildRef")) else {
+        debug!("The Image {} has no build to trigger", name);
+        return Ok(false);
+    };
+    let latest = latest.as_str().unwrap_or_default();
+
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace.as_str(), &build_api_resource());
+    let patch = json!({"metadata": {"annotations": {BUILD_NEEDED_ANNOTATION: "true"}}});
+    api.patch(latest, &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+    info!("Triggered a new build of Image {} from Build {}", name, latest);
+
+    Ok(true)
+}
+
+/// Check if the build is annotated to be rebuilt, but kpack has not picked it up yet.
+async fn build_needed(client: &Client, namespace: &str, name: &str) -> Result<bool> {
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace, &build_api_resource());
+    let build = api.get_opt(name).await.map_err(Error::KubeError)?;
+
+    Ok(build.is_some_and(|build| build.annotations().contains_key(BUILD_NEEDED_ANNOTATION)))
+}
+
 #[inline]
 fn api_resource() -> ApiResource {
     ApiResource::from_gvk(&GroupVersionKind::gvk("kpack.io", "v1alpha2", "Image"))
 }
 
+#[inline]
+fn build_api_resource() -> ApiResource {
+    ApiResource::from_gvk(&GroupVersionKind::gvk("kpack.io", "v1alpha2", "Build"))
+}
+
 fn new(actor: &Actor) -> Result<DynamicObject> {
     let name = format!("{}-builder", actor.spec.name);
     let owner_reference = actor.controller_owner_ref(&()).unwrap();
@@ -141,10 +194,99 @@ pub async fn completed(client: &Client, actor: &Actor) -> Result<bool> {
         if let Some(conditions) = image.data.pointer("/status/conditions") {
             let conditions: Vec<Condition> =
                 serde_json::from_value(json!(conditions)).map_err(Error::SerializationError)?;
-            return Ok(conditions.iter().any(|condition| condition.type_ == "Ready" && condition.status == "True"));
+            let ready = conditions.iter().any(|condition| condition.type_ == "Ready" && condition.status == "True");
+
+            // The image is still ready before kpack picks up the triggered build.
+            let latest = image.data.pointer("/status/latestBuildRef").and_then(Value::as_str);
+            if let Some(latest) = latest.filter(|_| ready) {
+                return Ok(!build_needed(client, &namespace, latest).await?);
+            }
+            return Ok(ready);
         }
     }
 
     debug!("Not found Image {}", &name);
     Ok(false)
 }
+
+/// Returns the reason if the latest Build of the Image failed (`Succeeded=False`).
+/// The Image itself is also `Ready=False` while its ClusterBuilder is not ready yet,
+/// e.g. they are just created together, which is not a failure of the build.
+pub async fn failed(client: &Client, actor: &Actor) -> Result<Option<String>> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace.as_str(), &api_resource());
+    let name = format!("{}-builder", actor.spec.name);
+
+    let image = api.get_opt(&name).await.map_err(Error::KubeError)?;
+    let Some(latest) = image.as_ref().and_then(|image| image.data.pointer("/status/latestBuildRef")) else {
+        return Ok(None);
+    };
+    let latest = latest.as_str().unwrap_or_default();
+
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace.as_str(), &build_api_resource());
+    let Some(build) = api.get_opt(latest).await.map_err(Error::KubeError)? else {
+        return Ok(None);
+    };
+    // The failed build is triggered again, but kpack has not picked it up yet.
+    if build.annotations().contains_key(BUILD_NEEDED_ANNOTATION) {
+        return Ok(None);
+    }
+
+    Ok(build_failed(&build.data).map(|message| format!("The build {latest} of Image {name} failed: {message}")))
+}
+
+/// Returns the message of the `Succeeded=False` condition of the kpack Build, if it failed.
+fn build_failed(build: &Value) -> Option<&str> {
+    // The conditions of kpack are not always complete, so don't parse them strictly.
+    let conditions = build.pointer("/status/conditions").and_then(Value::as_array)?;
+    let condition = conditions.iter().find(|c| c["type"] == "Succeeded" && c["status"] == "False")?;
+
+    Some(condition["message"].as_str().unwrap_or("unknown reason"))
+}
+
+/// Returns the digest of the latest image built by kpack, e.g. `sha256:...`.
+pub async fn digest(client: &Client, actor: &Actor) -> Result<Option<String>> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), namespace.as_str(), &api_resource());
+    let name = format!("{}-builder", actor.spec.name);
+
+    let image = api.get_opt(&name).await.map_err(Error::KubeError)?;
+    let latest = image.as_ref().and_then(|image| image.data.pointer("/status/latestImage")).and_then(Value::as_str);
+
+    Ok(latest.and_then(|latest| latest.split_once('@')).map(|(_, digest)| digest.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::build_failed;
+
+    #[test]
+    fn test_build_failed() {
+        let build = json!({"status": {"conditions": [
+            {"type": "Ready", "status": "False"},
+            {"type": "Succeeded", "status": "False", "message": "Build failed: exit status 51"},
+        ]}});
+        assert_eq!(build_failed(&build), Some("Build failed: exit status 51"));
+
+        let build = json!({"status": {"conditions": [{"type": "Succeeded", "status": "False"}]}});
+        assert_eq!(build_failed(&build), Some("unknown reason"));
+    }
+
+    #[test]
+    fn test_build_not_failed() {
+        // Running, succeeded, and not started builds.
+        let build = json!({"status": {"conditions": [{"type": "Succeeded", "status": "Unknown"}]}});
+        assert_eq!(build_failed(&build), None);
+        let build = json!({"status": {"conditions": [{"type": "Succeeded", "status": "True"}]}});
+        assert_eq!(build_failed(&build), None);
+        assert_eq!(build_failed(&json!({})), None);
+
+        // The Image condition of a ClusterBuilder not ready yet is not a failure of the build.
+        let build = json!({"status": {"conditions": [
+            {"type": "Ready", "status": "False", "reason": "BuilderNotReady", "message": "Builder is not ready"},
+        ]}});
+        assert_eq!(build_failed(&build), None);
+    }
+}
@@ -22,6 +22,64 @@ pub mod image;
 pub mod syncer;
 pub mod types;
 
+mod references;
+pub use self::references::References;
+
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::api::{DeleteParams, ListParams};
+use kube::core::DynamicObject;
+use kube::discovery::ApiResource;
+use kube::{Api, Client, ResourceExt};
+use tracing::{debug, info};
+
+use crate::error::{Error, Result};
+
+/// Check if kpack is installed in the cluster, by looking up the Image CRD.
+pub async fn installed(client: &Client) -> Result<bool> {
+    let api: Api<CustomResourceDefinition> = Api::all(client.clone());
+    Ok(api.get_opt("images.kpack.io").await.map_err(Error::KubeError)?.is_some())
+}
+
+/// List the names of cluster-scoped kpack objects managed by Amphitheatre,
+/// it's empty if the kpack CRDs are not installed.
+async fn list_managed(client: &Client, resource: &ApiResource) -> Result<Vec<String>> {
+    let api: Api<DynamicObject> = Api::all_with(client.clone(), resource);
+    let params = ListParams::default().labels("app.kubernetes.io/managed-by=Amphitheatre");
+
+    match api.list_metadata(&params).await {
+        Ok(objects) => Ok(objects.items.iter().map(|object| object.name_any()).collect()),
+        Err(kube::Error::Api(err)) if err.code == 404 => Ok(vec![]),
+        Err(err) => Err(Error::KubeError(err)),
+    }
+}
+
+/// Delete a cluster-scoped kpack object if it's managed by Amphitheatre,
+/// it's fine if the object does not exist.
+async fn delete_managed(client: &Client, resource: &ApiResource, name: &str) -> Result<()> {
+    let api: Api<DynamicObject> = Api::all_with(client.clone(), resource);
+
+    let object = match api.get_opt(name).await {
+        Ok(object) => object,
+        // The kpack CRDs may be not installed
+        Err(kube::Error::Api(err)) if err.code == 404 => None,
+        Err(err) => return Err(Error::KubeError(err)),
+    };
+    let Some(object) = object else {
+        debug!("Not found {} {}", resource.kind, name);
+        return Ok(());
+    };
+
+    if object.labels().get("app.kubernetes.io/managed-by").map(String::as_str) != Some("Amphitheatre") {
+        debug!("Skip the {} {} which is not managed by Amphitheatre", resource.kind, name);
+        return Ok(());
+    }
+
+    api.delete(name, &DeleteParams::background()).await.map_err(Error::KubeError)?;
+    info!("Deleted {}: {}", resource.kind, name);
+
+    Ok(())
+}
+
 /// Encodes the image url to a valid Kubernetes resource name
 /// e.g. "gcr.io/paketo-buildpacks/builder:base" -> "gcr-io-paketo-buildpacks-builder"
 /// e.g. "gcr.io/paketo-buildpacks/java@sha256:fc1c6fba46b582f63b13490b89e50e93c95ce08142a8737f4a6b70c826c995de" -> "gcr-io-paketo-buildpacks-java"
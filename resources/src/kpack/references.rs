@@ -0,0 +1,107 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::BTreeSet;
+
+use amp_common::resource::Actor;
+use amp_common::schema::BuildMethod;
+
+use super::{encode_name, BuildExt};
+
+/// The names of the cluster-scoped kpack objects used by actors. These objects can't
+/// be owned by the namespaced actors, so they are reference counted by the actors instead.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct References {
+    pub builders: BTreeSet<String>,
+    pub stores: BTreeSet<String>,
+    pub buildpacks: BTreeSet<String>,
+}
+
+impl References {
+    /// Collects the objects used by the given actors, only the actors built with buildpacks count.
+    pub fn from_actors<'a>(actors: impl IntoIterator<Item = &'a Actor>) -> Self {
+        let mut references = References::default();
+
+        for actor in actors {
+            let character = &actor.spec.character;
+            let build = character.build.clone().unwrap_or_default();
+            if !matches!(build.method(), BuildMethod::Buildpacks) {
+                continue;
+            }
+
+            references.builders.insert(character.builder_name());
+            references.stores.insert(character.store_name());
+            if let Some(buildpacks) = character.buildpacks() {
+                references.buildpacks.extend(buildpacks.iter().map(|image| encode_name(image)));
+            }
+        }
+
+        references
+    }
+
+    /// Returns the objects which are not used by the others.
+    pub fn difference(&self, others: &References) -> References {
+        References {
+            builders: self.builders.difference(&others.builders).cloned().collect(),
+            stores: self.stores.difference(&others.stores).cloned().collect(),
+            buildpacks: self.buildpacks.difference(&others.buildpacks).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.builders.is_empty() && self.stores.is_empty() && self.buildpacks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_common::resource::{ActorSpec, CharacterSpec};
+    use amp_common::schema::{Build, BuildpacksConfig};
+
+    use super::*;
+
+    fn actor(name: &str, builder: &str, buildpacks: Option<Vec<&str>>) -> Actor {
+        let buildpacks = buildpacks.map(|items| items.into_iter().map(String::from).collect());
+        let character = CharacterSpec {
+            build: Some(Build {
+                buildpacks: Some(BuildpacksConfig { builder: builder.to_string(), buildpacks }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        Actor::new(name, ActorSpec { name: name.into(), character, ..Default::default() })
+    }
+
+    #[test]
+    fn test_references_from_actors() {
+        let actors = [actor("a", "amp/builder:v1", None), actor("b", "amp/builder:v1", Some(vec!["amp/node"]))];
+        let references = References::from_actors(&actors);
+
+        assert_eq!(references.stores, BTreeSet::from(["amp-builder".to_string()]));
+        assert_eq!(references.buildpacks, BTreeSet::from(["amp-node".to_string()]));
+        assert_eq!(references.builders.len(), 2);
+    }
+
+    #[test]
+    fn test_references_difference() {
+        let deleting = actor("a", "amp/builder:v1", Some(vec!["amp/node", "amp/go"]));
+        let others = [actor("b", "amp/builder:v1", Some(vec!["amp/node"]))];
+
+        let unused = References::from_actors([&deleting]).difference(&References::from_actors(&others));
+        assert!(unused.stores.is_empty());
+        assert_eq!(unused.buildpacks, BTreeSet::from(["amp-go".to_string()]));
+        assert_eq!(unused.builders.len(), 1);
+    }
+}
@@ -15,13 +15,13 @@
 use std::collections::BTreeMap;
 
 use amp_common::resource::Actor;
-use k8s_openapi::api::core::v1::{PersistentVolumeClaimVolumeSource, Pod, PodSpec, Volume};
+use k8s_openapi::api::core::v1::{Pod, PodSpec};
 use kube::api::{Patch, PatchParams, PostParams};
 use kube::core::ObjectMeta;
 use kube::{Api, Client, Resource, ResourceExt};
 use tracing::{debug, info};
 
-use crate::containers::syncer;
+use crate::containers::{syncer, workspace_claim_volume};
 use crate::error::{Error, Result};
 use crate::{hash, LAST_APPLIED_HASH_KEY};
 
@@ -93,14 +93,7 @@ fn new(actor: &Actor) -> Result<Pod> {
         spec: Some(PodSpec {
             containers: vec![syncer::container(actor, &None)?],
             restart_policy: Some("Never".into()),
-            volumes: Some(vec![Volume {
-                name: "workspace".to_string(),
-                persistent_volume_claim: Some(PersistentVolumeClaimVolumeSource {
-                    claim_name: actor.spec.character.pvc_name(),
-                    read_only: Some(false),
-                }),
-                ..Default::default()
-            }]),
+            volumes: Some(vec![workspace_claim_volume(actor.spec.character.pvc_name())]),
             ..Default::default()
         }),
         ..Default::default()
@@ -13,6 +13,8 @@
 // limitations under the License.
 
 use base16ct::lower::encode_string;
+use jiff::Timestamp;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, Time};
 use serde::Serialize;
 use serde_json::to_string;
 use sha2::{Digest, Sha256};
@@ -20,11 +22,13 @@ use sha2::{Digest, Sha256};
 use self::error::{Error, Result};
 
 pub mod actor;
+pub mod build;
 pub mod character;
 pub mod containers;
 pub mod credential;
 pub mod deployment;
 pub mod error;
+pub mod hot_reload;
 pub mod job;
 pub mod kpack;
 pub mod namespace;
@@ -32,6 +36,7 @@ pub mod playbook;
 pub mod secret;
 pub mod service;
 pub mod service_account;
+pub mod sync;
 pub mod volume;
 
 const LAST_APPLIED_HASH_KEY: &str = "amphitheatre.app/last-applied-hash";
@@ -53,3 +58,64 @@ pub fn args(args: &[(&str, &str)], dash: i8) -> Vec<String> {
         .map(|(key, value)| if dash == 1 { format!("-{key}={value}") } else { format!("--{key}={value}") })
         .collect()
 }
+
+/// Returns a true condition with the given type, reason and message.
+pub(crate) fn condition(type_: &str, reason: &str, message: String) -> Condition {
+    Condition {
+        type_: type_.to_string(),
+        status: "True".to_string(),
+        reason: reason.to_string(),
+        message,
+        last_transition_time: Time(Timestamp::now()),
+        observed_generation: None,
+    }
+}
+
+/// Check if any of the true conditions is of the given state, case-insensitively, e.g. `running`.
+pub fn in_state(conditions: &[Condition], state: &str) -> bool {
+    conditions.iter().any(|c| c.status == "True" && c.type_.eq_ignore_ascii_case(state))
+}
+
+/// Returns the condition of the current phase: a true failed condition takes precedence,
+/// otherwise the latest true one. The out of sync flag of actors is not a phase.
+pub fn phase(conditions: &[Condition]) -> Option<&Condition> {
+    let phases = conditions.iter().filter(|c| c.status == "True" && c.type_ != actor::OUT_OF_SYNC);
+    phases.clone().find(|c| c.type_ == actor::FAILED).or_else(|| phases.max_by_key(|c| c.last_transition_time.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_state() {
+        let conditions = vec![
+            condition("Running", "AutoRun", String::new()),
+            Condition { status: "False".into(), ..condition("OutOfSync", "Resynced", String::new()) },
+        ];
+
+        assert!(in_state(&conditions, "running"));
+        assert!(in_state(&conditions, "Running"));
+        assert!(!in_state(&conditions, "outofsync"));
+        assert!(!in_state(&conditions, "failed"));
+        assert!(!in_state(&[], "running"));
+    }
+
+    #[test]
+    fn test_phase() {
+        assert!(phase(&[]).is_none());
+
+        let building = condition("Building", "Build", String::new());
+        let running = condition("Running", "AutoRun", String::new());
+        let out_of_sync = condition(actor::OUT_OF_SYNC, "DeadLettered", String::new());
+        let conditions = vec![building.clone(), running.clone(), out_of_sync];
+        assert_eq!(phase(&conditions).map(|c| c.type_.as_str()), Some("Running"));
+
+        let failed = condition(actor::FAILED, "BuildFailed", "The build failed".into());
+        let conditions = vec![failed, running];
+        assert_eq!(phase(&conditions).map(|c| c.type_.as_str()), Some("Failed"));
+
+        let conditions = vec![Condition { status: "False".into(), ..building }];
+        assert!(phase(&conditions).is_none());
+    }
+}
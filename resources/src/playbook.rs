@@ -12,9 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
 use std::time::Duration;
 
-use amp_common::resource::{CharacterSpec, Playbook, PlaybookState};
+use amp_common::resource::{CharacterSpec, Playbook, PlaybookSpec, PlaybookState};
 
 use k8s_openapi::apiextensions_apiserver as server;
 use server::pkg::apis::apiextensions::v1::CustomResourceDefinition;
@@ -24,12 +25,28 @@ use kube::api::{DeleteParams, ListParams, Patch, PatchParams, PostParams};
 use kube::core::ObjectList;
 use kube::{Api, Client, CustomResourceExt, ResourceExt};
 
+use base16ct::lower::encode_string;
 use serde_json::{json, to_string_pretty};
+use sha2::{Digest, Sha256};
 use tokio::time::sleep;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
+use super::condition;
 use super::error::{Error, Result};
 
+/// The condition type of a playbook which has been stopped by a user.
+pub const STOPPED: &str = "Stopped";
+
+/// The condition type of a stopped playbook which is being started again.
+pub const STARTING: &str = "Starting";
+
+/// The label and annotation of the user who owns the playbook,
+/// the label value is hashed if the user name is not a valid label value.
+pub const OWNER_LABEL: &str = "amphitheatre.app/owner";
+
+/// The annotation of the roles granted to the users on the playbook, it's a JSON object of user to role.
+pub const ROLES_ANNOTATION: &str = "amphitheatre.app/roles";
+
 pub async fn install(client: &Client) -> Result<()> {
     let api: Api<CustomResourceDefinition> = Api::all(client.clone());
     let crd = Playbook::crd();
@@ -89,6 +106,9 @@ pub async fn add(client: &Client, playbook: &Playbook, character: CharacterSpec)
     if let Some(items) = &playbook.spec.characters {
         characters.clone_from(items);
     }
+
+    // Replace the existing character with the same name, e.g. the preface was changed.
+    characters.retain(|item| item.meta.name != character_name);
     characters.push(character);
 
     let params = &PatchParams::apply("amp-controllers");
@@ -100,6 +120,27 @@ pub async fn add(client: &Client, playbook: &Playbook, character: CharacterSpec)
     Ok(())
 }
 
+/// Update the spec of a playbook. If the resource version is given, Kubernetes
+/// rejects the update with a conflict when the playbook was changed since then.
+pub async fn update(
+    client: &Client,
+    name: &str,
+    spec: &PlaybookSpec,
+    resource_version: Option<String>,
+) -> Result<Playbook> {
+    let api: Api<Playbook> = Api::all(client.clone());
+
+    let mut patch = json!({ "spec": spec });
+    if let Some(version) = resource_version {
+        patch["metadata"] = json!({ "resourceVersion": version });
+    }
+
+    let playbook = api.patch(name, &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+    info!("Updated playbook: {}", playbook.name_any());
+
+    Ok(playbook)
+}
+
 pub async fn patch_status(client: &Client, playbook: &Playbook, condition: Condition) -> Result<()> {
     let api: Api<Playbook> = Api::all(client.clone());
 
@@ -113,10 +154,10 @@ pub async fn patch_status(client: &Client, playbook: &Playbook, condition: Condi
     Ok(())
 }
 
-/// List all playbooks
-pub async fn list(client: &Client) -> Result<ObjectList<Playbook>> {
+/// List the playbooks, the params carry the label selector and the continuation.
+pub async fn list(client: &Client, params: &ListParams) -> Result<ObjectList<Playbook>> {
     let api: Api<Playbook> = Api::all(client.clone());
-    api.list(&ListParams::default()).await.map_err(Error::KubeError)
+    api.list(params).await.map_err(Error::KubeError)
 }
 
 /// Get a playbook by name
@@ -143,3 +184,138 @@ pub async fn delete(client: &Client, name: &str) -> Result<()> {
 
     Ok(())
 }
+
+/// Returns the condition of a playbook stopped by the given user.
+pub fn stopped(by: &str) -> Condition {
+    condition(STOPPED, "Stopped", format!("Stopped by {by}"))
+}
+
+/// Returns the condition of a stopped playbook started again by the given user.
+pub fn starting(by: &str) -> Condition {
+    condition(STARTING, "Started", format!("Started by {by}"))
+}
+
+/// Check if the playbook is stopped.
+pub fn is_stopped(playbook: &Playbook) -> bool {
+    has_condition(playbook, STOPPED)
+}
+
+/// Check if the playbook is being started again after stopped.
+pub fn is_starting(playbook: &Playbook) -> bool {
+    has_condition(playbook, STARTING)
+}
+
+/// Returns the current (true) condition of the given type, if any.
+pub fn find_condition<'a>(playbook: &'a Playbook, type_: &str) -> Option<&'a Condition> {
+    playbook
+        .status
+        .as_ref()
+        .and_then(|status| status.conditions.iter().find(|c| c.type_ == type_ && c.status == "True"))
+}
+
+/// Set the owner of the playbook before creating it.
+pub fn set_owner(playbook: &mut Playbook, user: &str) {
+    playbook.labels_mut().insert(OWNER_LABEL.into(), owner(user));
+    playbook.annotations_mut().insert(OWNER_LABEL.into(), user.into());
+}
+
+/// Check if the playbook is owned by the user.
+pub fn is_owned_by(playbook: &Playbook, user: &str) -> bool {
+    playbook.labels().get(OWNER_LABEL).is_some_and(|value| *value == owner(user))
+}
+
+/// Replace the roles granted to the users on the playbook.
+pub async fn set_grants(client: &Client, name: &str, grants: &BTreeMap<String, String>) -> Result<()> {
+    let api: Api<Playbook> = Api::all(client.clone());
+
+    let value = serde_json::to_string(grants).map_err(Error::SerializationError)?;
+    let patch = json!({ "metadata": { "annotations": { ROLES_ANNOTATION: value } } });
+    api.patch(name, &PatchParams::default(), &Patch::Merge(&patch)).await.map_err(Error::KubeError)?;
+    info!("Granted roles {:?} on playbook {}", grants, name);
+
+    Ok(())
+}
+
+/// Returns the roles granted to the users on the playbook, the invalid annotation grants nothing.
+pub fn grants(playbook: &Playbook) -> BTreeMap<String, String> {
+    playbook
+        .annotations()
+        .get(ROLES_ANNOTATION)
+        .and_then(|value| serde_json::from_str(value).inspect_err(|err| warn!("Invalid roles: {}", err)).ok())
+        .unwrap_or_default()
+}
+
+/// Returns the owner label value of the user, the names which are not valid label values
+/// (e.g. emails) or look like a hashed one are hashed, so that they never collide.
+fn owner(user: &str) -> String {
+    let valid = (1..=63).contains(&user.len())
+        && user.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && user.ends_with(|c: char| c.is_ascii_alphanumeric())
+        && user.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+    if valid && !user.starts_with("sha256-") {
+        return user.to_string();
+    }
+
+    let hash = encode_string(&Sha256::digest(user.as_bytes()));
+    format!("sha256-{}", &hash[..56])
+}
+
+#[inline]
+fn has_condition(playbook: &Playbook, type_: &str) -> bool {
+    find_condition(playbook, type_).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use amp_common::resource::PlaybookSpec;
+
+    use super::*;
+
+    #[test]
+    fn test_stopped_condition() {
+        let condition = stopped("alice");
+
+        assert_eq!(condition.type_, STOPPED);
+        assert_eq!(condition.status, "True");
+        assert_eq!(condition.reason, "Stopped");
+        assert_eq!(condition.message, "Stopped by alice");
+    }
+
+    #[test]
+    fn test_is_stopped() {
+        let mut playbook = Playbook::new("test", PlaybookSpec::default());
+        assert!(!is_stopped(&playbook));
+
+        playbook.status = Some(Default::default());
+        playbook.status.as_mut().unwrap().conditions = vec![stopped("alice")];
+        assert!(is_stopped(&playbook));
+        assert!(!is_starting(&playbook));
+    }
+
+    #[test]
+    fn test_owner() {
+        assert_eq!(owner("alice"), "alice");
+        assert_eq!(owner("alice@example.com").len(), 63);
+        assert!(owner("alice@example.com").starts_with("sha256-"));
+        assert_ne!(owner(&owner("alice@example.com")), owner("alice@example.com"));
+
+        let mut playbook = Playbook::new("test", PlaybookSpec::default());
+        assert!(!is_owned_by(&playbook, "alice"));
+
+        set_owner(&mut playbook, "alice@example.com");
+        assert!(is_owned_by(&playbook, "alice@example.com"));
+        assert!(!is_owned_by(&playbook, "bob@example.com"));
+    }
+
+    #[test]
+    fn test_grants() {
+        let mut playbook = Playbook::new("test", PlaybookSpec::default());
+        assert!(grants(&playbook).is_empty());
+
+        playbook.annotations_mut().insert(ROLES_ANNOTATION.into(), r#"{"bob":"viewer"}"#.into());
+        assert_eq!(grants(&playbook), BTreeMap::from([("bob".to_string(), "viewer".to_string())]));
+
+        playbook.annotations_mut().insert(ROLES_ANNOTATION.into(), "bob=viewer".into());
+        assert!(grants(&playbook).is_empty());
+    }
+}
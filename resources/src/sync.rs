@@ -0,0 +1,180 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+
+// The NATS contract of the synchronization, shared by the apiserver which publishes
+// the synchronizations, the syncer which applies them, and the controllers watching it.
+
+/// The headers of a chunked synchronization, which is larger than the NATS max payload.
+pub const CHUNK_ID: &str = "Amp-Chunk-Id";
+pub const CHUNK_INDEX: &str = "Amp-Chunk-Index";
+pub const CHUNK_TOTAL: &str = "Amp-Chunk-Total";
+
+/// Reserved for the headers and protocol overhead of a NATS message.
+pub const CHUNK_OVERHEAD: usize = 1024;
+
+/// The key-value bucket of the sync status of actors, keyed by `{playbook}.{actor}`.
+pub const STATUS_BUCKET: &str = "amp-sync-status";
+
+/// The headers of dead letters, added to the original headers of the failed synchronization.
+pub const DEAD_LETTER_ACTOR: &str = "Amp-Dead-Letter-Actor";
+pub const DEAD_LETTER_REASON: &str = "Amp-Dead-Letter-Reason";
+pub const DEAD_LETTER_SEQUENCE: &str = "Amp-Dead-Letter-Sequence";
+
+/// The number of recent errors kept in the status.
+const MAX_ERRORS: usize = 20;
+
+/// Returns the max size of each chunk under the NATS max payload, at least one byte.
+pub fn chunk_size(max_payload: usize) -> usize {
+    max_payload.saturating_sub(CHUNK_OVERHEAD).max(1)
+}
+
+/// Returns the dead-letter subject of the playbook, it's in the playbook stream.
+/// It's uppercase so that it never conflicts with the actor names.
+pub fn dead_letter_subject(playbook: &str) -> String {
+    format!("{playbook}.DLQ")
+}
+
+/// Returns the subject of the results reported by the syncer of the actor.
+pub fn results_subject(playbook: &str, actor: &str) -> String {
+    format!("{playbook}.{actor}.results")
+}
+
+/// The result of handling a synchronization message.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "lowercase")]
+pub enum Outcome {
+    Applied,
+    Failed {
+        reason: String,
+    },
+    Skipped {
+        reason: String,
+    },
+    /// Failed too many times and moved to the dead-letter subject of playbook.
+    #[serde(rename = "dead-lettered")]
+    DeadLettered {
+        reason: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SyncResult {
+    /// The stream sequence of the message, the last one if it's chunked.
+    pub sequence: u64,
+    /// The kind of synchronization event, unknown if the message is invalid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    /// The paths touched by the applied message, relative to the workspace.
+    /// None if they are unknown or too many.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paths: Option<Vec<String>>,
+}
+
+/// The sync status of the actor, stored in the key-value bucket.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    /// The sequence of the last handled message, whatever the outcome is.
+    pub last_sequence: Option<u64>,
+    /// The sequence of the last applied message.
+    pub last_applied_sequence: Option<u64>,
+    /// The recent failed or skipped messages, the newest last.
+    pub errors: Vec<SyncResult>,
+}
+
+impl Status {
+    pub fn record(&mut self, result: SyncResult) {
+        self.last_sequence = self.last_sequence.max(Some(result.sequence));
+        if result.outcome == Outcome::Applied {
+            self.last_applied_sequence = self.last_applied_sequence.max(Some(result.sequence));
+            return;
+        }
+
+        self.errors.push(result);
+        if self.errors.len() > MAX_ERRORS {
+            self.errors.drain(..self.errors.len() - MAX_ERRORS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn failed(sequence: u64) -> SyncResult {
+        SyncResult { sequence, kind: None, outcome: Outcome::Failed { reason: "denied".into() }, paths: None }
+    }
+
+    #[test]
+    fn test_record() {
+        let mut status = Status::default();
+        status.record(SyncResult {
+            sequence: 1,
+            kind: Some("Modify".into()),
+            outcome: Outcome::Applied,
+            paths: Some(vec!["src/main.rs".into()]),
+        });
+        status.record(failed(2));
+
+        assert_eq!(status.last_sequence, Some(2));
+        assert_eq!(status.last_applied_sequence, Some(1));
+        assert_eq!(status.errors, vec![failed(2)]);
+    }
+
+    #[test]
+    fn test_record_keeps_recent_errors() {
+        let mut status = Status::default();
+        for sequence in 1..=30 {
+            status.record(failed(sequence));
+        }
+
+        assert_eq!(status.errors.len(), MAX_ERRORS);
+        assert_eq!(status.errors.first(), Some(&failed(11)));
+        assert_eq!(status.last_applied_sequence, None);
+    }
+
+    #[test]
+    fn test_serialize_result() {
+        let result = serde_json::to_value(failed(2)).unwrap();
+        assert_eq!(result, json!({"sequence": 2, "outcome": "failed", "reason": "denied"}));
+
+        let result: SyncResult =
+            serde_json::from_value(json!({"sequence": 3, "kind": "Modify", "outcome": "applied", "paths": ["a"]}))
+                .unwrap();
+        assert_eq!(result.outcome, Outcome::Applied);
+        assert_eq!(result.paths, Some(vec!["a".to_string()]));
+
+        let result: SyncResult =
+            serde_json::from_value(json!({"sequence": 4, "outcome": "dead-lettered", "reason": "poison"})).unwrap();
+        assert_eq!(result.outcome, Outcome::DeadLettered { reason: "poison".into() });
+    }
+
+    #[test]
+    fn test_chunk_size() {
+        assert_eq!(chunk_size(1024 * 1024), 1024 * 1024 - CHUNK_OVERHEAD);
+        assert_eq!(chunk_size(CHUNK_OVERHEAD), 1);
+        assert_eq!(chunk_size(0), 1);
+    }
+
+    #[test]
+    fn test_subjects() {
+        assert_eq!(dead_letter_subject("pid"), "pid.DLQ");
+        assert_eq!(results_subject("pid", "web"), "pid.web.results");
+    }
+}
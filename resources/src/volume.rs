@@ -18,7 +18,7 @@ use std::env;
 use amp_common::resource::Actor;
 use k8s_openapi::api::core::v1::{PersistentVolumeClaim, PersistentVolumeClaimSpec, VolumeResourceRequirements};
 use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
-use kube::api::PostParams;
+use kube::api::{DeleteParams, PostParams};
 use kube::core::ObjectMeta;
 use kube::{Api, Client, Resource, ResourceExt};
 use tracing::{debug, info};
@@ -47,6 +47,21 @@ pub async fn create(client: &Client, actor: &Actor) -> Result<PersistentVolumeCl
     Ok(pvc)
 }
 
+/// Delete the PersistentVolumeClaim of the actor, it's fine if it does not exist.
+pub async fn delete(client: &Client, actor: &Actor) -> Result<()> {
+    let namespace = actor.namespace().ok_or_else(|| Error::MissingObjectKey(".metadata.namespace"))?;
+    let api: Api<PersistentVolumeClaim> = Api::namespaced(client.clone(), namespace.as_str());
+    let name = actor.spec.character.pvc_name();
+
+    match api.delete(&name, &DeleteParams::default()).await {
+        Ok(_) => info!("Deleted PersistentVolumeClaim: {}", name),
+        Err(kube::Error::Api(err)) if err.code == 404 => debug!("Not found PersistentVolumeClaim {}", name),
+        Err(err) => return Err(Error::KubeError(err)),
+    }
+
+    Ok(())
+}
+
 fn new(actor: &Actor) -> Result<PersistentVolumeClaim> {
     let name = actor.spec.character.pvc_name();
     let owner_reference = actor.controller_owner_ref(&()).unwrap();
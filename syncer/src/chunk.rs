@@ -0,0 +1,197 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use amp_resources::sync::{CHUNK_ID, CHUNK_INDEX, CHUNK_TOTAL};
+use async_nats::HeaderMap;
+use tracing::warn;
+
+/// A part of a message which is larger than the NATS max payload.
+#[derive(Debug, PartialEq)]
+pub struct Chunk {
+    pub id: String,
+    pub index: usize,
+    pub total: usize,
+}
+
+impl Chunk {
+    /// Parse the chunk from the message headers, `None` if it's not chunked.
+    pub fn from_headers(headers: Option<&HeaderMap>) -> Option<Chunk> {
+        let headers = headers?;
+        let id = headers.get(CHUNK_ID)?.as_str().to_string();
+        let index = headers.get(CHUNK_INDEX)?.as_str().parse().ok()?;
+        let total = headers.get(CHUNK_TOTAL)?.as_str().parse().ok()?;
+
+        Some(Chunk { id, index, total })
+    }
+}
+
+/// The pending chunks of a message are dropped if it's not complete in time, e.g. the publisher
+/// died in the middle. Their messages are never acknowledged, so the stream redelivers them.
+const PENDING_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The max number of pending messages, the oldest one is dropped to make room for a new one.
+const MAX_PENDING: usize = 16;
+
+/// Reassembles the chunked messages, keeping the items (e.g. the messages to acknowledge)
+/// of each chunk until the whole payload is received.
+pub struct Assembler<T> {
+    pending: HashMap<String, Pending<T>>,
+    timeout: Duration,
+    capacity: usize,
+}
+
+struct Pending<T> {
+    parts: Vec<Option<(Vec<u8>, T)>>,
+    since: Instant,
+}
+
+impl<T> Default for Assembler<T> {
+    fn default() -> Self {
+        Assembler { pending: HashMap::new(), timeout: PENDING_TIMEOUT, capacity: MAX_PENDING }
+    }
+}
+
+impl<T> Assembler<T> {
+    /// Push a chunk, returns the whole payload and the items of all chunks (in order) once it's complete.
+    pub fn push(&mut self, chunk: Chunk, payload: &[u8], item: T) -> Option<(Vec<u8>, Vec<T>)> {
+        if chunk.total == 0 || chunk.index >= chunk.total {
+            warn!("Received invalid chunk: {:?}", chunk);
+            return None;
+        }
+
+        self.expire();
+        if !self.pending.contains_key(&chunk.id) && self.pending.len() >= self.capacity {
+            self.evict();
+        }
+
+        let pending = self
+            .pending
+            .entry(chunk.id.clone())
+            .or_insert_with(|| Pending { parts: (0..chunk.total).map(|_| None).collect(), since: Instant::now() });
+        if pending.parts.len() != chunk.total {
+            warn!("Received chunk with mismatched total: {:?}", chunk);
+            return None;
+        }
+
+        // The redelivered chunks replace the previous ones, together with their items.
+        pending.parts[chunk.index] = Some((payload.to_vec(), item));
+        if pending.parts.iter().any(Option::is_none) {
+            return None;
+        }
+
+        let pending = self.pending.remove(&chunk.id)?;
+        let (parts, items): (Vec<Vec<u8>>, Vec<T>) = pending.parts.into_iter().flatten().unzip();
+        Some((parts.concat(), items))
+    }
+
+    /// Drop the pending messages which are not complete in time.
+    fn expire(&mut self) {
+        let timeout = self.timeout;
+        self.pending.retain(|id, pending| {
+            let expired = pending.since.elapsed() >= timeout;
+            if expired {
+                warn!("Dropped the incomplete chunked message {} after {:?}", id, timeout);
+            }
+            !expired
+        });
+    }
+
+    /// Drop the oldest pending message.
+    fn evict(&mut self) {
+        let oldest = self.pending.iter().min_by_key(|(_, pending)| pending.since).map(|(id, _)| id.clone());
+        if let Some(id) = oldest {
+            warn!("Dropped the incomplete chunked message {}, too many are pending", id);
+            self.pending.remove(&id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(index: usize, total: usize) -> Chunk {
+        Chunk { id: "1".into(), index, total }
+    }
+
+    fn chunk_of(id: &str, index: usize, total: usize) -> Chunk {
+        Chunk { id: id.into(), index, total }
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CHUNK_ID, "1");
+        headers.insert(CHUNK_INDEX, "0");
+        headers.insert(CHUNK_TOTAL, "2");
+
+        assert_eq!(Chunk::from_headers(Some(&headers)), Some(chunk(0, 2)));
+        assert_eq!(Chunk::from_headers(Some(&HeaderMap::new())), None);
+        assert_eq!(Chunk::from_headers(None), None);
+    }
+
+    #[test]
+    fn test_assemble_out_of_order() {
+        let mut assembler = Assembler::default();
+
+        assert_eq!(assembler.push(chunk(2, 3), b"!", 'c'), None);
+        assert_eq!(assembler.push(chunk(0, 3), b"hello", 'a'), None);
+        assert_eq!(assembler.push(chunk(1, 3), b" world", 'b'), Some((b"hello world!".to_vec(), vec!['a', 'b', 'c'])));
+        assert!(assembler.pending.is_empty());
+    }
+
+    #[test]
+    fn test_assemble_invalid_chunk() {
+        let mut assembler = Assembler::default();
+
+        assert_eq!(assembler.push(chunk(1, 1), b"hello", 'a'), None);
+        assert_eq!(assembler.push(chunk(0, 1), b"hello", 'a'), Some((b"hello".to_vec(), vec!['a'])));
+    }
+
+    #[test]
+    fn test_assemble_redelivered_chunk() {
+        let mut assembler = Assembler::default();
+
+        assert_eq!(assembler.push(chunk(0, 2), b"hello", 'a'), None);
+        assert_eq!(assembler.push(chunk(0, 2), b"hello", 'A'), None);
+        assert_eq!(assembler.push(chunk(1, 2), b" world", 'b'), Some((b"hello world".to_vec(), vec!['A', 'b'])));
+    }
+
+    #[test]
+    fn test_expire_pending() {
+        let mut assembler = Assembler { timeout: Duration::ZERO, ..Assembler::default() };
+
+        assert_eq!(assembler.push(chunk_of("1", 0, 2), b"hello", 'a'), None);
+        assert_eq!(assembler.push(chunk_of("2", 0, 2), b"hello", 'b'), None);
+        assert_eq!(assembler.pending.keys().collect::<Vec<_>>(), vec!["2"]);
+    }
+
+    #[test]
+    fn test_evict_oldest_pending() {
+        let mut assembler = Assembler { capacity: 2, ..Assembler::default() };
+
+        assert_eq!(assembler.push(chunk_of("1", 0, 2), b"a", 'a'), None);
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(assembler.push(chunk_of("2", 0, 2), b"b", 'b'), None);
+        assert_eq!(assembler.push(chunk_of("3", 0, 2), b"c", 'c'), None);
+        assert!(!assembler.pending.contains_key("1"));
+        assert_eq!(assembler.pending.len(), 2);
+
+        // The chunks of the pending ones are still assembled.
+        assert_eq!(assembler.push(chunk_of("2", 1, 2), b"!", 'B'), Some((b"b!".to_vec(), vec!['b', 'B'])));
+    }
+}
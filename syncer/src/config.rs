@@ -43,4 +43,7 @@ pub struct Config {
     // Exit after sync once (Overwrite).
     #[clap(long, action = clap::ArgAction::Set, default_value = "false", env = "AMP_ONCE")]
     pub once: bool,
+    /// Watch the workspace and publish the changes made inside the container, not in once mode.
+    #[clap(long, action = clap::ArgAction::Set, default_value = "true", env = "AMP_WATCH")]
+    pub watch: bool,
 }
@@ -0,0 +1,55 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::time::Duration;
+
+use amp_resources::sync::{dead_letter_subject, DEAD_LETTER_ACTOR, DEAD_LETTER_REASON, DEAD_LETTER_SEQUENCE};
+use async_nats::jetstream::{self, Message};
+use async_nats::HeaderMap;
+
+/// The max number of deliveries of a message, it's dead-lettered after the last attempt failed.
+pub const MAX_DELIVER: i64 = 5;
+
+/// The delay before a failed message is redelivered.
+pub const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Check if any of the messages has run out of attempts.
+pub fn exhausted(messages: &[Message]) -> bool {
+    messages.iter().filter_map(|message| message.info().ok()).any(|info| info.delivered >= MAX_DELIVER)
+}
+
+/// Republish the messages to the dead-letter subject, with the original headers and the reason.
+pub async fn publish(
+    jetstream: &jetstream::Context,
+    playbook: &str,
+    actor: &str,
+    messages: &[Message],
+    reason: &str,
+) -> Result<(), async_nats::Error> {
+    // The header values must be single line.
+    let reason = reason.replace(['\r', '\n'], " ");
+
+    for message in messages {
+        let mut headers = message.headers.clone().unwrap_or_else(HeaderMap::new);
+        headers.insert(DEAD_LETTER_ACTOR, actor);
+        headers.insert(DEAD_LETTER_REASON, reason.as_str());
+        if let Ok(info) = message.info() {
+            headers.insert(DEAD_LETTER_SEQUENCE, info.stream_sequence.to_string().as_str());
+        }
+
+        jetstream.publish_with_headers(dead_letter_subject(playbook), headers, message.payload.clone()).await?.await?;
+    }
+
+    Ok(())
+}
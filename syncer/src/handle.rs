@@ -12,20 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use amp_common::sync::{self, Synchronization};
 use std::io::Result;
-use tar::Archive;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
+
+use crate::ignore_rules::IgnoreRules;
+use crate::sandbox;
 
 /// Overwrite workspace's files with payload tarball.
-pub fn overwrite(workspace: &Path, req: &Synchronization) -> Result<()> {
+pub fn overwrite(workspace: &Path, req: &Synchronization, ignore: &IgnoreRules) -> Result<()> {
     debug!("Received overwrite event, workspace: {:?}, req: {:?}", workspace, req);
 
     if let Some(payload) = &req.payload {
-        let mut ar = Archive::new(payload.as_slice());
-        ar.unpack(workspace)?;
+        sandbox::unpack(workspace, payload, ignore)?;
         info!("Received overwrite event and unpacked into workspace: {:?}", workspace);
     }
 
@@ -33,12 +34,17 @@ pub fn overwrite(workspace: &Path, req: &Synchronization) -> Result<()> {
 }
 
 /// Create new files or directories.
-pub fn create(workspace: &Path, req: &Synchronization) -> Result<()> {
+pub fn create(workspace: &Path, req: &Synchronization, ignore: &IgnoreRules) -> Result<()> {
     debug!("Received create event, workspace: {:?}, req: {:?}", workspace, req);
     for path in &req.paths {
         match path {
             sync::Path::File(file) => {
-                let path = workspace.join(file);
+                if ignore.is_ignored(file, false) {
+                    debug!("Skipped ignored file: {:?}", file);
+                    continue;
+                }
+
+                let path = sandbox::join(workspace, file)?;
                 if path.exists() {
                     warn!("Path already exists: {:?}", path);
                     continue;
@@ -54,7 +60,12 @@ pub fn create(workspace: &Path, req: &Synchronization) -> Result<()> {
                 info!("Created file: {:?}", path);
             }
             sync::Path::Directory(path) => {
-                let path = workspace.join(path);
+                if ignore.is_ignored(path, true) {
+                    debug!("Skipped ignored directory: {:?}", path);
+                    continue;
+                }
+
+                let path = sandbox::join(workspace, path)?;
                 if path.exists() {
                     warn!("Path already exists: {:?}", path);
                     continue;
@@ -70,32 +81,93 @@ pub fn create(workspace: &Path, req: &Synchronization) -> Result<()> {
 }
 
 /// Modify existing files or directories.
-pub fn modify(workspace: &Path, req: &Synchronization) -> Result<()> {
+pub fn modify(workspace: &Path, req: &Synchronization, ignore: &IgnoreRules) -> Result<()> {
     debug!("Received modify event, workspace: {:?}, req: {:?}", workspace, req);
 
     if let Some(payload) = &req.payload {
-        let mut ar = Archive::new(payload.as_slice());
-        ar.unpack(workspace)?;
+        sandbox::unpack(workspace, payload, ignore)?;
         info!("Received modify event and unpacked into workspace: {:?}", workspace);
     }
 
     Ok(())
 }
 
-/// Rename existing files or directories.
-pub fn rename(_workspace: &Path, _req: &Synchronization) -> Result<()> {
-    error!("Received rename event, nothing to do!");
+/// Rename existing files or directories, the paths are paired as source and destination.
+/// If the source does not exist, fall back to modify the destination with the payload.
+pub fn rename(workspace: &Path, req: &Synchronization, ignore: &IgnoreRules) -> Result<()> {
+    debug!("Received rename event, workspace: {:?}, req: {:?}", workspace, req);
+
+    if req.paths.len() % 2 != 0 {
+        warn!("Received rename event with unpaired paths: {:?}", req.paths);
+    }
+
+    let mut missing = false;
+    for pair in req.paths.chunks_exact(2) {
+        if ignored(ignore, &pair[1]) {
+            debug!("Skipped the rename to ignored path: {:?}", pair[1]);
+            continue;
+        }
+
+        let (from, _) = resolve(workspace, &pair[0])?;
+        let (to, directory) = resolve(workspace, &pair[1])?;
+
+        // The ignored source is never deleted, so create the destination instead.
+        if ignored(ignore, &pair[0]) || !from.exists() {
+            warn!("Path does not exist or is ignored: {:?}, modify {:?} instead", from, to);
+            missing = true;
+            continue;
+        }
+
+        if let Some(parent) = to.parent() {
+            if !parent.exists() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        // A directory can't be renamed to an existing non-empty one, replace it.
+        if directory && to.is_dir() {
+            std::fs::remove_dir_all(&to)?;
+        }
+
+        std::fs::rename(&from, &to)?;
+        info!("Renamed {:?} to {:?}", from, to);
+    }
+
+    if missing {
+        return modify(workspace, req, ignore);
+    }
+
     Ok(())
 }
 
+fn ignored(ignore: &IgnoreRules, path: &sync::Path) -> bool {
+    match path {
+        sync::Path::File(file) => ignore.is_ignored(file, false),
+        sync::Path::Directory(directory) => ignore.is_ignored(directory, true),
+    }
+}
+
+/// Resolve the path in workspace, and whether it's a directory.
+fn resolve(workspace: &Path, path: &sync::Path) -> Result<(PathBuf, bool)> {
+    match path {
+        sync::Path::File(file) => Ok((sandbox::join(workspace, file)?, false)),
+        sync::Path::Directory(directory) => Ok((sandbox::join(workspace, directory)?, true)),
+    }
+}
+
 /// Remove existing files or directories.
-pub fn remove(workspace: &Path, req: &Synchronization) -> Result<()> {
+pub fn remove(workspace: &Path, req: &Synchronization, ignore: &IgnoreRules) -> Result<()> {
     debug!("Received remove event, workspace: {:?}, req: {:?}", workspace, req);
 
     for path in &req.paths {
         match path {
             sync::Path::File(file) => {
-                let path = workspace.join(file);
+                if ignore.is_ignored(file, false) {
+                    debug!("Skipped ignored file: {:?}", file);
+                    continue;
+                }
+
+                let path = sandbox::join(workspace, file)?;
                 if !path.exists() {
                     warn!("Path does not exist: {:?}", path);
                     continue;
@@ -105,7 +177,12 @@ pub fn remove(workspace: &Path, req: &Synchronization) -> Result<()> {
                 info!("Removed file: {:?}", path);
             }
             sync::Path::Directory(path) => {
-                let path = workspace.join(path);
+                if ignore.is_ignored(path, true) {
+                    debug!("Skipped ignored directory: {:?}", path);
+                    continue;
+                }
+
+                let path = sandbox::join(workspace, path)?;
                 if !path.exists() {
                     warn!("Path does not exist: {:?}", path);
                     continue;
@@ -119,3 +196,116 @@ pub fn remove(workspace: &Path, req: &Synchronization) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use amp_common::sync::EventKinds;
+    use tar::{Builder, Header};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn rename_event(paths: Vec<sync::Path>) -> Synchronization {
+        let mut req: Synchronization = serde_json::from_value(serde_json::json!({
+            "kind": EventKinds::Rename,
+            "paths": [],
+        }))
+        .unwrap();
+        req.paths = paths;
+        req
+    }
+
+    fn tarball(path: &str, content: &[u8]) -> Vec<u8> {
+        let mut header = Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+
+        let mut builder = Builder::new(Vec::new());
+        builder.append_data(&mut header, path, content).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_rename_file() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join("a.txt"), "hello").unwrap();
+
+        let req = rename_event(vec![sync::Path::File("a.txt".into()), sync::Path::File("src/b.txt".into())]);
+        rename(workspace.path(), &req, &IgnoreRules::load(workspace.path())).unwrap();
+
+        assert!(!workspace.path().join("a.txt").exists());
+        assert_eq!(std::fs::read_to_string(workspace.path().join("src/b.txt")).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_rename_directory() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::create_dir_all(workspace.path().join("old/nested")).unwrap();
+        std::fs::write(workspace.path().join("old/nested/a.txt"), "hello").unwrap();
+        std::fs::create_dir_all(workspace.path().join("new")).unwrap();
+        std::fs::write(workspace.path().join("new/stale.txt"), "stale").unwrap();
+
+        let req = rename_event(vec![sync::Path::Directory("old".into()), sync::Path::Directory("new".into())]);
+        rename(workspace.path(), &req, &IgnoreRules::load(workspace.path())).unwrap();
+
+        assert!(!workspace.path().join("old").exists());
+        assert!(!workspace.path().join("new/stale.txt").exists());
+        assert_eq!(std::fs::read_to_string(workspace.path().join("new/nested/a.txt")).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_rename_ignored() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join(".ampignore"), "*.log\n").unwrap();
+        std::fs::write(workspace.path().join("a.txt"), "hello").unwrap();
+        std::fs::write(workspace.path().join("b.log"), "hello").unwrap();
+
+        let req = rename_event(vec![
+            sync::Path::File("a.txt".into()),
+            sync::Path::File("a.log".into()),
+            sync::Path::File("b.log".into()),
+            sync::Path::File("b.txt".into()),
+        ]);
+        rename(workspace.path(), &req, &IgnoreRules::load(workspace.path())).unwrap();
+
+        assert!(workspace.path().join("a.txt").exists());
+        assert!(!workspace.path().join("a.log").exists());
+        assert!(workspace.path().join("b.log").exists());
+    }
+
+    #[test]
+    fn test_remove_ignored() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join(".ampignore"), "target/\n").unwrap();
+        std::fs::create_dir(workspace.path().join("target")).unwrap();
+
+        let mut req = rename_event(vec![sync::Path::Directory("target".into())]);
+        req.kind = EventKinds::Remove;
+        remove(workspace.path(), &req, &IgnoreRules::load(workspace.path())).unwrap();
+
+        assert!(workspace.path().join("target").exists());
+    }
+
+    #[test]
+    fn test_rename_outside_workspace() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join("a.txt"), "hello").unwrap();
+
+        let req = rename_event(vec![sync::Path::File("a.txt".into()), sync::Path::File("../b.txt".into())]);
+        assert!(rename(workspace.path(), &req, &IgnoreRules::load(workspace.path())).is_err());
+        assert!(workspace.path().join("a.txt").exists());
+    }
+
+    #[test]
+    fn test_rename_missing_source_modifies_destination() {
+        let workspace = TempDir::new().unwrap();
+
+        let mut req = rename_event(vec![sync::Path::File("a.txt".into()), sync::Path::File("b.txt".into())]);
+        req.payload = Some(tarball("b.txt", b"hello"));
+        rename(workspace.path(), &req, &IgnoreRules::load(workspace.path())).unwrap();
+
+        assert!(!workspace.path().join("a.txt").exists());
+        assert_eq!(std::fs::read_to_string(workspace.path().join("b.txt")).unwrap(), "hello");
+    }
+}
@@ -0,0 +1,146 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::{Component, Path, PathBuf};
+
+use async_nats::Client;
+use futures::StreamExt;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+/// The files of ignore rules in the workspace root, in the order of precedence.
+pub const RULE_FILES: [&str; 2] = [".gitignore", ".ampignore"];
+
+/// The patterns always ignored, whatever the rule files say.
+const BUILTIN_PATTERNS: [&str; 1] = [".git/"];
+
+/// The ignore rules of the workspace, the ignored paths are never written or deleted.
+pub struct IgnoreRules {
+    patterns: Vec<String>,
+    matcher: Gitignore,
+}
+
+#[derive(Debug, Serialize)]
+struct IgnoreResponse<'a> {
+    patterns: &'a [String],
+}
+
+impl IgnoreRules {
+    /// Load the rules from the builtin patterns, `.gitignore` and `.ampignore` in the workspace root.
+    pub fn load(workspace: &Path) -> IgnoreRules {
+        let mut patterns: Vec<String> = BUILTIN_PATTERNS.iter().map(|pattern| pattern.to_string()).collect();
+        for file in RULE_FILES {
+            if let Ok(content) = std::fs::read_to_string(workspace.join(file)) {
+                patterns.extend(
+                    content
+                        .lines()
+                        .map(str::trim_end)
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .map(String::from),
+                );
+            }
+        }
+
+        let mut builder = GitignoreBuilder::new(workspace);
+        for pattern in &patterns {
+            if let Err(err) = builder.add_line(None, pattern) {
+                warn!("Skipped invalid ignore pattern {:?}: {}", pattern, err);
+            }
+        }
+        let matcher = builder.build().unwrap_or_else(|err| {
+            warn!("Failed to build the ignore rules: {}", err);
+            Gitignore::empty()
+        });
+
+        IgnoreRules { patterns, matcher }
+    }
+
+    /// Check if the path relative to the workspace, or any of its parents, is ignored.
+    pub fn is_ignored(&self, path: impl AsRef<Path>, is_dir: bool) -> bool {
+        let path: PathBuf = path.as_ref().components().filter(|c| !matches!(c, Component::CurDir)).collect();
+        if path.as_os_str().is_empty() || path.is_absolute() {
+            return false;
+        }
+
+        self.matcher.matched_path_or_any_parents(&path, is_dir).is_ignore()
+    }
+
+    /// Returns the patterns of the rules, so that clients can filter the paths before uploading.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+}
+
+/// Serve the ignore rules requests on the subject, reply with the patterns of the workspace.
+pub async fn serve(client: Client, subject: String, workspace: PathBuf) -> Result<(), async_nats::Error> {
+    let mut subscriber = client.subscribe(subject.clone()).await?;
+    info!("Serving ignore rules requests on subject: {}", subject);
+
+    while let Some(message) = subscriber.next().await {
+        let Some(reply) = message.reply else {
+            warn!("Received ignore rules request without reply subject, ignored");
+            continue;
+        };
+        debug!("Received ignore rules request");
+
+        let rules = IgnoreRules::load(&workspace);
+        let response = IgnoreResponse { patterns: rules.patterns() };
+        client.publish(reply, serde_json::to_vec(&response)?.into()).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_is_ignored() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join(".gitignore"), "# build\n/target\nnode_modules/\n*.log\n").unwrap();
+        std::fs::write(workspace.path().join(".ampignore"), "!keep.log\n").unwrap();
+
+        let rules = IgnoreRules::load(workspace.path());
+        assert!(rules.is_ignored("target/debug/app", false));
+        assert!(rules.is_ignored("./node_modules/react/index.js", false));
+        assert!(rules.is_ignored("web/node_modules", true));
+        assert!(rules.is_ignored("debug.log", false));
+        assert!(rules.is_ignored(".git/HEAD", false));
+        assert!(!rules.is_ignored("keep.log", false));
+        assert!(!rules.is_ignored("src/target.rs", false));
+        assert!(!rules.is_ignored(".gitignore", false));
+    }
+
+    #[test]
+    fn test_patterns() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join(".ampignore"), "# local\n\ndist/\n").unwrap();
+
+        let rules = IgnoreRules::load(workspace.path());
+        assert_eq!(rules.patterns(), [".git/", "dist/"]);
+    }
+
+    #[test]
+    fn test_without_rule_files() {
+        let workspace = TempDir::new().unwrap();
+
+        let rules = IgnoreRules::load(workspace.path());
+        assert!(!rules.is_ignored("node_modules/react/index.js", false));
+        assert!(rules.is_ignored(".git/config", false));
+    }
+}
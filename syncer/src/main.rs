@@ -13,22 +13,36 @@
 // limitations under the License.
 
 use std::path::Path;
+use std::sync::Arc;
 
 use amp_common::sync::EventKinds::*;
 use amp_common::sync::Synchronization;
+use amp_resources::containers::syncer;
+use amp_resources::sync::Outcome;
 use async_nats::jetstream::consumer::{pull, PullConsumer};
-use async_nats::jetstream::{self, stream};
+use async_nats::jetstream::{self, stream, AckKind};
+use chunk::{Assembler, Chunk};
 use clap::Parser;
 use config::Config;
 use futures::StreamExt;
+use ignore_rules::IgnoreRules;
+use status::Reporter;
 use tracing::metadata::LevelFilter;
 use tracing::{debug, error, info, warn};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
+use watcher::Suppressor;
 
+mod chunk;
 mod config;
+mod dead_letter;
 mod handle;
+mod ignore_rules;
+mod manifest;
+mod sandbox;
+mod status;
+mod watcher;
 
 #[tokio::main]
 async fn main() -> Result<(), async_nats::Error> {
@@ -51,43 +65,126 @@ async fn main() -> Result<(), async_nats::Error> {
     let workspace = Path::new(&config.workspace);
 
     debug!("Connecting to NATS server: {}", config.nats_url);
-    let consumer = connect(&config).await?;
+    let client = async_nats::connect(&config.nats_url).await?;
+    let jetstream = jetstream::new(client.clone());
+    let consumer = connect(&jetstream, &config).await?;
+
+    // Report the result of each message, so that clients know when their changes are live.
+    let mut reporter = Reporter::new(client.clone(), &jetstream, &config.playbook, &config.actor).await?;
+
+    // Serve the manifest requests, so that only the missing or changed files are synced.
+    let subject = format!("{}.{}.manifest", config.playbook, config.actor);
+    let (manifest_client, root) = (client.clone(), workspace.to_path_buf());
+    tokio::spawn(async move {
+        if let Err(err) = manifest::serve(manifest_client, subject, root).await {
+            error!("Failed to serve manifest requests: {}", err);
+        }
+    });
+
+    // Serve the ignore rules, so that clients can filter the paths before uploading.
+    let subject = format!("{}.{}.ignore", config.playbook, config.actor);
+    let (ignore_client, root) = (client.clone(), workspace.to_path_buf());
+    tokio::spawn(async move {
+        if let Err(err) = ignore_rules::serve(ignore_client, subject, root).await {
+            error!("Failed to serve ignore rules requests: {}", err);
+        }
+    });
+
+    // Publish the changes made inside the container back to the clients.
+    let suppressor = Arc::new(Suppressor::default());
+    if config.watch && !config.once {
+        let subject = format!("{}.{}.out", config.playbook, config.actor);
+        let (watch_client, root, suppressor) = (client.clone(), workspace.to_path_buf(), suppressor.clone());
+        tokio::spawn(async move {
+            if let Err(err) = watcher::watch(watch_client, subject, root, suppressor).await {
+                error!("Failed to watch the workspace: {}", err);
+            }
+        });
+    }
 
     // Consume messages from the consumer
     let mut messages = consumer.messages().await?;
+    let mut assembler = Assembler::default();
     while let Some(Ok(message)) = messages.next().await {
-        let synchronization = serde_json::from_slice(message.payload.as_ref());
+        // Reassemble the messages which are chunked to stay under the NATS max payload.
+        let (payload, parts) = match Chunk::from_headers(message.headers.as_ref()) {
+            Some(chunk) => {
+                let payload = message.payload.clone();
+                match assembler.push(chunk, &payload, message) {
+                    Some(assembled) => assembled,
+                    None => continue,
+                }
+            }
+            None => (message.payload.to_vec(), vec![message]),
+        };
+
+        let sequence = sequence(&parts);
+        let synchronization = serde_json::from_slice(&payload);
         if let Err(err) = synchronization {
-            error!("Received invalid message: {:?} with error: {:?}", message.payload, err);
+            error!("Received invalid message: {:?} with error: {:?}", payload, err);
+            // It's never valid even if redelivered.
+            let outcome = Outcome::Skipped { reason: format!("Invalid message: {err}") };
+            reporter.report(sequence, None, outcome).await;
+            acknowledge(&parts, AckKind::Term).await;
             continue;
         }
 
         let req: Synchronization = synchronization.unwrap();
         debug!("Received valid message: kind={:?} paths={:?}", req.kind, req.paths);
+        let kind = Some(format!("{:?}", req.kind));
+
+        // The changes written by ourselves are not published back.
+        let touched = watcher::touched(&req);
+        suppressor.record(touched.clone());
+
+        // Load the ignore rules every time, they may be changed by the last message.
+        let ignore = IgnoreRules::load(workspace);
 
         // Handle the message
-        if let Err(err) = match req.kind {
-            Create => handle::create(workspace, &req),
-            Modify => handle::modify(workspace, &req),
-            Rename => handle::rename(workspace, &req),
-            Remove => handle::remove(workspace, &req),
-            Overwrite => handle::overwrite(workspace, &req),
+        let handled = match req.kind {
+            Create => handle::create(workspace, &req, &ignore),
+            Modify => handle::modify(workspace, &req, &ignore),
+            Rename => handle::rename(workspace, &req, &ignore),
+            Remove => handle::remove(workspace, &req, &ignore),
+            Overwrite => handle::overwrite(workspace, &req, &ignore),
             Other => {
                 warn!("Received other event, nothing to do!");
-                Ok(())
+                reporter.report(sequence, kind, Outcome::Skipped { reason: "Unsupported event".into() }).await;
+                acknowledge(&parts, AckKind::Ack).await;
+                continue;
             }
-        } {
+        };
+        suppressor.record(touched.clone());
+
+        if let Err(err) = handled {
             // If we failed to handle the message, log the error and continue.
             // We don't want to crash the application because of a single message.
             // We can always retry later, but the next time retry,
             // the original intent may no longer be valid!!!
             error!("Failed to handle message: {}", err);
+            let reason = err.to_string();
+
+            // Move the message to the dead-letter subject if it runs out of attempts,
+            // so that it never wedges the consumer.
+            if dead_letter::exhausted(&parts) {
+                match dead_letter::publish(&jetstream, &config.playbook, &config.actor, &parts, &reason).await {
+                    Ok(()) => {
+                        warn!("Moved message {} to the dead-letter subject", sequence);
+                        reporter.report(sequence, kind, Outcome::DeadLettered { reason }).await;
+                        acknowledge(&parts, AckKind::Term).await;
+                        continue;
+                    }
+                    Err(err) => error!("Failed to publish dead letter: {}", err),
+                }
+            }
+
+            reporter.report(sequence, kind, Outcome::Failed { reason }).await;
+            acknowledge(&parts, AckKind::Nak(Some(dead_letter::RETRY_DELAY))).await;
             continue;
         }
         // Acknowledge the message if we handled it successfully.
-        if let Err(err) = message.ack().await {
-            error!("Failed to acknowledge message: {:?}", err);
-        }
+        reporter.applied(sequence, kind, &touched).await;
+        acknowledge(&parts, AckKind::Ack).await;
 
         // If we're in once mode, exit after overwrite.
         if config.once && req.kind == Overwrite {
@@ -99,15 +196,26 @@ async fn main() -> Result<(), async_nats::Error> {
     Ok(())
 }
 
-/// Connect to NATS server and return a consumer.
-async fn connect(config: &Config) -> Result<PullConsumer, async_nats::Error> {
-    // Connect to NATS server and create a JetStream instance.
-    let client = async_nats::connect(&config.nats_url).await?;
-    let jetstream = jetstream::new(client);
+/// Acknowledge all messages which carry the synchronization.
+async fn acknowledge(messages: &[jetstream::Message], kind: AckKind) {
+    for message in messages {
+        if let Err(err) = message.ack_with(kind).await {
+            error!("Failed to acknowledge message: {:?}", err);
+        }
+    }
+}
+
+/// Returns the stream sequence of the synchronization, the last one if it's chunked.
+fn sequence(messages: &[jetstream::Message]) -> u64 {
+    messages.iter().filter_map(|message| message.info().ok()).map(|info| info.stream_sequence).max().unwrap_or_default()
+}
 
+/// Get or create a stream and return a consumer.
+async fn connect(jetstream: &jetstream::Context, config: &Config) -> Result<PullConsumer, async_nats::Error> {
     // get or create a stream and a consumer
     let subject = format!("{}.{}", config.playbook, config.actor);
-    let name = "amp-syncer";
+    // Each actor has its own durable consumer on the playbook stream.
+    let name = syncer::consumer_name(&config.actor);
     let consumer = jetstream
         // First, on the `JetStream` instance, use method to create Stream.
         .get_or_create_stream(stream::Config {
@@ -118,10 +226,11 @@ async fn connect(config: &Config) -> Result<PullConsumer, async_nats::Error> {
         .await?
         // Then, on that `Stream` use method to create Consumer and bind to it.
         .get_or_create_consumer(
-            name,
+            &name,
             pull::Config {
-                durable_name: Some(name.to_string()),
+                durable_name: Some(name.clone()),
                 filter_subject: subject.clone(),
+                max_deliver: dead_letter::MAX_DELIVER,
                 ..Default::default()
             },
         )
@@ -0,0 +1,142 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use async_nats::Client;
+use base16ct::lower::encode_string;
+use futures::StreamExt;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{debug, error, info, warn};
+
+use crate::ignore_rules::IgnoreRules;
+use crate::sandbox;
+
+/// A file in the manifest of the client's workspace.
+#[derive(Debug, Deserialize)]
+pub struct Entry {
+    /// The path relative to the workspace.
+    pub path: String,
+    /// The size of file in bytes.
+    pub size: u64,
+    /// The lowercase hex encoded SHA-256 of file content.
+    pub hash: String,
+}
+
+/// Serve the manifest requests on the subject, reply with the paths which are
+/// missing or different in the workspace, so that only those are synced.
+pub async fn serve(client: Client, subject: String, workspace: PathBuf) -> Result<(), async_nats::Error> {
+    let mut subscriber = client.subscribe(subject.clone()).await?;
+    info!("Serving manifest requests on subject: {}", subject);
+
+    while let Some(message) = subscriber.next().await {
+        let Some(reply) = message.reply else {
+            warn!("Received manifest request without reply subject, ignored");
+            continue;
+        };
+
+        let entries: Vec<Entry> = match serde_json::from_slice(message.payload.as_ref()) {
+            Ok(entries) => entries,
+            Err(err) => {
+                error!("Received invalid manifest request: {:?}", err);
+                continue;
+            }
+        };
+        debug!("Received manifest request with {} entries", entries.len());
+
+        let root = workspace.clone();
+        let missing = tokio::task::spawn_blocking(move || missing(&root, &IgnoreRules::load(&root), &entries)).await?;
+        client.publish(reply, serde_json::to_vec(&missing)?.into()).await?;
+    }
+
+    Ok(())
+}
+
+/// Returns the paths of entries which are missing or different in the workspace, except the ignored ones.
+pub fn missing(workspace: &Path, ignore: &IgnoreRules, entries: &[Entry]) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|entry| !ignore.is_ignored(&entry.path, false) && !matches(workspace, entry))
+        .map(|entry| entry.path.clone())
+        .collect()
+}
+
+/// Compare size first, then the hash of file content.
+fn matches(workspace: &Path, entry: &Entry) -> bool {
+    let path = match sandbox::join(workspace, &entry.path) {
+        Ok(path) => path,
+        Err(err) => {
+            // It's rejected anyway, so never ask for it.
+            warn!("Skipped manifest entry {}: {}", entry.path, err);
+            return true;
+        }
+    };
+
+    match std::fs::metadata(&path) {
+        Ok(metadata) if metadata.is_file() && metadata.len() == entry.size => {}
+        _ => return false,
+    }
+
+    hash(&path).is_ok_and(|hash| hash.eq_ignore_ascii_case(&entry.hash))
+}
+
+fn hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+
+    Ok(encode_string(&hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn entry(path: &str, content: &str) -> Entry {
+        Entry { path: path.into(), size: content.len() as u64, hash: encode_string(&Sha256::digest(content)) }
+    }
+
+    #[test]
+    fn test_missing() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::write(workspace.path().join("same.txt"), "hello").unwrap();
+        std::fs::write(workspace.path().join("changed.txt"), "hello").unwrap();
+        std::fs::write(workspace.path().join("resized.txt"), "hello").unwrap();
+
+        let entries = vec![
+            entry("same.txt", "hello"),
+            entry("changed.txt", "world"),
+            entry("resized.txt", "hello world"),
+            entry("new.txt", "hello"),
+            entry("../escape.txt", "hello"),
+            entry(".git/HEAD", "hello"),
+        ];
+
+        let ignore = IgnoreRules::load(workspace.path());
+        assert_eq!(missing(workspace.path(), &ignore, &entries), vec!["changed.txt", "resized.txt", "new.txt"]);
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Component, Path, PathBuf};
+
+use tar::{Archive, EntryType};
+use tracing::debug;
+
+use crate::ignore_rules::IgnoreRules;
+
+/// Join the relative path to the workspace, reject it if it leaves the workspace,
+/// either by absolute paths, `..` components or symbolic links inside the workspace.
+pub fn join(workspace: &Path, path: impl AsRef<Path>) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let relative = normalize(path).ok_or_else(|| escaped(path))?;
+    let joined = workspace.join(relative);
+
+    // The existing part of the path may be symbolic links which point to outside.
+    let root = workspace.canonicalize()?;
+    let mut existing = joined.as_path();
+    while existing.symlink_metadata().is_err() {
+        match existing.parent() {
+            Some(parent) => existing = parent,
+            None => break,
+        }
+    }
+    if !existing.canonicalize()?.starts_with(&root) {
+        return Err(escaped(path));
+    }
+
+    Ok(joined)
+}
+
+/// Unpack the tarball into the workspace, reject it if any entry or link leaves the workspace.
+/// The ignored entries are skipped.
+pub fn unpack(workspace: &Path, payload: &[u8], ignore: &IgnoreRules) -> Result<()> {
+    let mut archive = Archive::new(payload);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        if ignore.is_ignored(&path, entry.header().entry_type().is_dir()) {
+            debug!("Skipped ignored entry {:?}", path);
+            continue;
+        }
+        join(workspace, &path)?;
+
+        if let Some(link) = entry.link_name()? {
+            // The target of symbolic links is relative to the entry, the hard links is relative to the root.
+            let target = match entry.header().entry_type() {
+                EntryType::Symlink => path.parent().unwrap_or(Path::new("")).join(&link),
+                _ => link.to_path_buf(),
+            };
+            if link.is_absolute() || normalize(&target).is_none() {
+                return Err(escaped(&link));
+            }
+        }
+
+        if !entry.unpack_in(workspace)? {
+            return Err(escaped(&path));
+        }
+        debug!("Unpacked {:?} into workspace", path);
+    }
+
+    Ok(())
+}
+
+/// Normalize the relative path lexically, `None` if it's absolute or leaves the root.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => normalized.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(normalized)
+}
+
+#[inline]
+fn escaped(path: &Path) -> Error {
+    Error::new(ErrorKind::PermissionDenied, format!("Path escapes the workspace: {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use tar::{Builder, Header};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// Create a workspace inside a temporary directory, so that escapes can be detected.
+    fn workspace() -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let workspace = dir.path().join("workspace");
+        std::fs::create_dir(&workspace).unwrap();
+        (dir, workspace)
+    }
+
+    /// Append an entry with raw path and link name, bypassing the validation of the tar builder.
+    fn append(builder: &mut Builder<Vec<u8>>, kind: EntryType, path: &str, link: &str, content: &[u8]) {
+        let mut header = Header::new_gnu();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+        header.set_entry_type(kind);
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, content).unwrap();
+    }
+
+    fn tarball(entries: &[(EntryType, &str, &str)]) -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for (kind, path, link) in entries {
+            let content: &[u8] = if *kind == EntryType::Regular { b"evil" } else { b"" };
+            append(&mut builder, *kind, path, link, content);
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("a/./b/../c")), Some(PathBuf::from("a/c")));
+        assert_eq!(normalize(Path::new("a/../../b")), None);
+        assert_eq!(normalize(Path::new("/etc/passwd")), None);
+    }
+
+    #[test]
+    fn test_join() {
+        let (dir, workspace) = workspace();
+        symlink(dir.path(), workspace.join("link")).unwrap();
+
+        assert_eq!(join(&workspace, "src/main.rs").unwrap(), workspace.join("src/main.rs"));
+        assert!(join(&workspace, "../escape.txt").is_err());
+        assert!(join(&workspace, "/etc/passwd").is_err());
+        assert!(join(&workspace, "link/escape.txt").is_err());
+    }
+
+    #[test]
+    fn test_unpack() {
+        let (_dir, workspace) = workspace();
+        let payload =
+            tarball(&[(EntryType::Regular, "src/main.rs", ""), (EntryType::Symlink, "src/lib.rs", "main.rs")]);
+
+        unpack(&workspace, &payload, &IgnoreRules::load(&workspace)).unwrap();
+        assert_eq!(std::fs::read_to_string(workspace.join("src/lib.rs")).unwrap(), "evil");
+    }
+
+    #[test]
+    fn test_unpack_ignored() {
+        let (_dir, workspace) = workspace();
+        std::fs::write(workspace.join(".ampignore"), "node_modules/\n").unwrap();
+        let payload = tarball(&[(EntryType::Regular, "node_modules/a.js", ""), (EntryType::Regular, "a.js", "")]);
+
+        unpack(&workspace, &payload, &IgnoreRules::load(&workspace)).unwrap();
+        assert!(!workspace.join("node_modules").exists());
+        assert!(workspace.join("a.js").exists());
+    }
+
+    #[test]
+    fn test_unpack_parent_dir_entry() {
+        let (dir, workspace) = workspace();
+        let payload = tarball(&[(EntryType::Regular, "../escape.txt", "")]);
+
+        assert!(unpack(&workspace, &payload, &IgnoreRules::load(&workspace)).is_err());
+        assert!(!dir.path().join("escape.txt").exists());
+    }
+
+    #[test]
+    fn test_unpack_absolute_entry() {
+        let (dir, workspace) = workspace();
+        let path = dir.path().join("escape.txt");
+        let payload = tarball(&[(EntryType::Regular, path.to_str().unwrap(), "")]);
+
+        assert!(unpack(&workspace, &payload, &IgnoreRules::load(&workspace)).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_unpack_escaping_symlink() {
+        let (dir, workspace) = workspace();
+        let payload = tarball(&[(EntryType::Symlink, "link", ".."), (EntryType::Regular, "link/escape.txt", "")]);
+
+        assert!(unpack(&workspace, &payload, &IgnoreRules::load(&workspace)).is_err());
+        assert!(!workspace.join("link").exists());
+        assert!(!dir.path().join("escape.txt").exists());
+    }
+
+    #[test]
+    fn test_unpack_escaping_hardlink() {
+        let (_dir, workspace) = workspace();
+        let payload = tarball(&[(EntryType::Link, "passwd", "/etc/passwd")]);
+
+        assert!(unpack(&workspace, &payload, &IgnoreRules::load(&workspace)).is_err());
+        assert!(!workspace.join("passwd").exists());
+    }
+
+    #[test]
+    fn test_unpack_through_existing_symlink() {
+        let (dir, workspace) = workspace();
+        symlink(dir.path(), workspace.join("link")).unwrap();
+        let payload = tarball(&[(EntryType::Regular, "link/escape.txt", "")]);
+
+        assert!(unpack(&workspace, &payload, &IgnoreRules::load(&workspace)).is_err());
+        assert!(!dir.path().join("escape.txt").exists());
+    }
+}
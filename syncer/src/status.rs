@@ -0,0 +1,94 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::PathBuf;
+
+use amp_resources::sync::{results_subject, Outcome, Status, SyncResult, STATUS_BUCKET};
+use async_nats::jetstream::{self, kv};
+use async_nats::Client;
+use tracing::{debug, error};
+
+/// The max number of touched paths in a result, it's considered as touching everything if exceeded.
+const MAX_PATHS: usize = 1000;
+
+/// Reports the result of each message on the reply subject, and keeps the status up to date.
+pub struct Reporter {
+    client: Client,
+    store: kv::Store,
+    subject: String,
+    key: String,
+    status: Status,
+}
+
+impl Reporter {
+    pub async fn new(
+        client: Client,
+        jetstream: &jetstream::Context,
+        playbook: &str,
+        actor: &str,
+    ) -> Result<Reporter, async_nats::Error> {
+        let store = match jetstream.get_key_value(STATUS_BUCKET).await {
+            Ok(store) => store,
+            Err(_) => {
+                jetstream
+                    .create_key_value(kv::Config { bucket: STATUS_BUCKET.into(), history: 1, ..Default::default() })
+                    .await?
+            }
+        };
+
+        // Continue with the previous status if the syncer is restarted.
+        let key = format!("{playbook}.{actor}");
+        let status = match store.get(&key).await? {
+            Some(value) => serde_json::from_slice(&value).unwrap_or_default(),
+            None => Status::default(),
+        };
+
+        Ok(Reporter { client, store, subject: results_subject(playbook, actor), key, status })
+    }
+
+    /// Publish the result and update the status, the failures are logged only.
+    pub async fn report(&mut self, sequence: u64, kind: Option<String>, outcome: Outcome) {
+        self.publish(SyncResult { sequence, kind, outcome, paths: None }).await;
+    }
+
+    /// Report the message is applied, with the paths it touched.
+    pub async fn applied(&mut self, sequence: u64, kind: Option<String>, touched: &[PathBuf]) {
+        let paths = (touched.len() <= MAX_PATHS)
+            .then(|| touched.iter().map(|path| path.to_string_lossy().into_owned()).collect());
+        self.publish(SyncResult { sequence, kind, outcome: Outcome::Applied, paths }).await;
+    }
+
+    async fn publish(&mut self, result: SyncResult) {
+        debug!("Reporting sync result: {:?}", result);
+
+        match serde_json::to_vec(&result) {
+            Ok(payload) => {
+                if let Err(err) = self.client.publish(self.subject.clone(), payload.into()).await {
+                    error!("Failed to publish sync result: {}", err);
+                }
+            }
+            Err(err) => error!("Failed to serialize sync result: {}", err),
+        }
+
+        self.status.record(result);
+        match serde_json::to_vec(&self.status) {
+            Ok(value) => {
+                if let Err(err) = self.store.put(&self.key, value.into()).await {
+                    error!("Failed to update sync status: {}", err);
+                }
+            }
+            Err(err) => error!("Failed to serialize sync status: {}", err),
+        }
+    }
+}
@@ -0,0 +1,255 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use amp_common::sync::{self, EventKinds, Synchronization};
+use async_nats::Client;
+use notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tar::{Archive, Builder};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+use crate::ignore_rules::{IgnoreRules, RULE_FILES};
+
+/// The changes within this window after the syncer wrote the paths are its own.
+const SUPPRESSION_WINDOW: Duration = Duration::from_secs(2);
+
+/// Remembers the paths written by the syncer itself, so that they are not published
+/// back to the clients which sent them, otherwise the changes bounce forever.
+#[derive(Default)]
+pub struct Suppressor {
+    recent: Mutex<Vec<(PathBuf, Instant)>>,
+}
+
+impl Suppressor {
+    /// Record the paths relative to the workspace, which are about to be written or just written.
+    pub fn record(&self, paths: Vec<PathBuf>) {
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|(_, at)| now.duration_since(*at) < SUPPRESSION_WINDOW);
+        recent.extend(paths.into_iter().map(|path| (path, now)));
+    }
+
+    /// Check if the path relative to the workspace, its parents or children are written recently.
+    pub fn suppressed(&self, path: &Path) -> bool {
+        let now = Instant::now();
+        let recent = self.recent.lock().unwrap();
+        recent.iter().any(|(recorded, at)| {
+            now.duration_since(*at) < SUPPRESSION_WINDOW && (recorded.starts_with(path) || path.starts_with(recorded))
+        })
+    }
+}
+
+/// Returns the paths relative to the workspace which are written by the synchronization.
+pub fn touched(req: &Synchronization) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = req.paths.iter().map(relative).collect();
+
+    if let Some(payload) = &req.payload {
+        if let Ok(entries) = Archive::new(payload.as_slice()).entries() {
+            paths.extend(entries.flatten().filter_map(|entry| entry.path().ok().map(|path| path.into_owned())));
+        }
+    }
+
+    // The tar entries may be prefixed with `./`, but the watched paths are not.
+    paths.iter().map(|path| path.components().filter(|c| !matches!(c, Component::CurDir)).collect()).collect()
+}
+
+/// Watch the workspace and publish the changes made inside the container on the subject.
+pub async fn watch(
+    client: Client,
+    subject: String,
+    workspace: PathBuf,
+    suppressor: Arc<Suppressor>,
+) -> Result<(), async_nats::Error> {
+    let workspace = workspace.canonicalize()?;
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+        Ok(event) => {
+            let _ = sender.send(event);
+        }
+        Err(err) => error!("Failed to watch the workspace: {}", err),
+    })?;
+    watcher.watch(&workspace, RecursiveMode::Recursive)?;
+    info!("Watching the workspace {:?} and publishing changes on subject: {}", workspace, subject);
+
+    let mut ignore = IgnoreRules::load(&workspace);
+    while let Some(event) = receiver.recv().await {
+        let relatives: Vec<PathBuf> =
+            event.paths.iter().filter_map(|path| path.strip_prefix(&workspace).ok().map(Path::to_path_buf)).collect();
+        if relatives.iter().any(|path| RULE_FILES.iter().any(|file| path == Path::new(file))) {
+            ignore = IgnoreRules::load(&workspace);
+        }
+
+        // The ignored paths, e.g. build outputs and dependencies, are not published either.
+        let skipped =
+            |path: &PathBuf| suppressor.suppressed(path) || ignore.is_ignored(path, workspace.join(path).is_dir());
+        if relatives.is_empty() || relatives.iter().any(skipped) {
+            continue;
+        }
+
+        let Some(req) = synchronization(&workspace, &event) else {
+            continue;
+        };
+        debug!("Publishing workspace change: kind={:?} paths={:?}", req.kind, req.paths);
+
+        match serde_json::to_vec(&req) {
+            Ok(payload) => {
+                if let Err(err) = client.publish(subject.clone(), payload.into()).await {
+                    warn!("Failed to publish workspace change: {}", err);
+                }
+            }
+            Err(err) => error!("Failed to serialize workspace change: {}", err),
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert the file system event to a synchronization, the modified files are carried in the payload.
+fn synchronization(workspace: &Path, event: &Event) -> Option<Synchronization> {
+    let name = |path: &PathBuf| path.strip_prefix(workspace).ok().map(|path| path.to_string_lossy().into_owned());
+    let first = event.paths.first()?;
+
+    let (kind, paths, archived) = match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+            let to = &event.paths[1];
+            let paths = if to.is_dir() {
+                vec![sync::Path::Directory(name(first)?.into()), sync::Path::Directory(name(to)?.into())]
+            } else {
+                vec![sync::Path::File(name(first)?.into()), sync::Path::File(name(to)?.into())]
+            };
+            (EventKinds::Rename, paths, to.is_file().then_some(to))
+        }
+        EventKind::Create(CreateKind::Folder) => {
+            (EventKinds::Create, vec![sync::Path::Directory(name(first)?.into())], None)
+        }
+        EventKind::Create(_)
+        | EventKind::Modify(ModifyKind::Data(_))
+        | EventKind::Modify(ModifyKind::Any)
+        | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            if first.is_dir() {
+                (EventKinds::Create, vec![sync::Path::Directory(name(first)?.into())], None)
+            } else if first.is_file() {
+                (EventKinds::Modify, vec![sync::Path::File(name(first)?.into())], Some(first))
+            } else {
+                return None;
+            }
+        }
+        EventKind::Remove(RemoveKind::Folder) => {
+            (EventKinds::Remove, vec![sync::Path::Directory(name(first)?.into())], None)
+        }
+        EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+            (EventKinds::Remove, vec![sync::Path::File(name(first)?.into())], None)
+        }
+        _ => return None,
+    };
+
+    let mut req: Synchronization = serde_json::from_value(serde_json::json!({ "kind": kind, "paths": [] })).ok()?;
+    req.paths = paths;
+    if let Some(path) = archived {
+        req.payload = Some(archive(path, &name(path)?).ok()?);
+    }
+
+    Some(req)
+}
+
+/// Pack the file into a tarball with the name relative to the workspace.
+fn archive(path: &Path, name: &str) -> std::io::Result<Vec<u8>> {
+    let mut builder = Builder::new(Vec::new());
+    builder.append_path_with_name(path, name)?;
+    builder.into_inner()
+}
+
+fn relative(path: &sync::Path) -> PathBuf {
+    match path {
+        sync::Path::File(file) => PathBuf::from(file),
+        sync::Path::Directory(directory) => PathBuf::from(directory),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use notify::event::DataChange;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_suppressed() {
+        let suppressor = Suppressor::default();
+        suppressor.record(vec![PathBuf::from("src/main.rs")]);
+
+        assert!(suppressor.suppressed(Path::new("src/main.rs")));
+        assert!(suppressor.suppressed(Path::new("src")));
+        assert!(!suppressor.suppressed(Path::new("src/lib.rs")));
+        assert!(!suppressor.suppressed(Path::new("Cargo.lock")));
+    }
+
+    #[test]
+    fn test_touched() {
+        let mut builder = Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_cksum();
+        builder.append_data(&mut header, "./src/main.rs", "hello".as_bytes()).unwrap();
+
+        let mut req: Synchronization =
+            serde_json::from_value(serde_json::json!({ "kind": EventKinds::Modify, "paths": [] })).unwrap();
+        req.paths = vec![sync::Path::File("Cargo.toml".into())];
+        req.payload = Some(builder.into_inner().unwrap());
+
+        assert_eq!(touched(&req), vec![PathBuf::from("Cargo.toml"), PathBuf::from("src/main.rs")]);
+    }
+
+    #[test]
+    fn test_synchronization_modify() {
+        let workspace = TempDir::new().unwrap();
+        let path = workspace.path().join("Cargo.lock");
+        std::fs::write(&path, "hello").unwrap();
+
+        let event = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content))).add_path(path);
+        let req = synchronization(workspace.path(), &event).unwrap();
+
+        assert_eq!(req.kind, EventKinds::Modify);
+        assert_eq!(touched(&req), vec![PathBuf::from("Cargo.lock"), PathBuf::from("Cargo.lock")]);
+    }
+
+    #[test]
+    fn test_synchronization_rename() {
+        let workspace = TempDir::new().unwrap();
+        std::fs::create_dir(workspace.path().join("new")).unwrap();
+
+        let event = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path(workspace.path().join("old"))
+            .add_path(workspace.path().join("new"));
+        let req = synchronization(workspace.path(), &event).unwrap();
+
+        assert_eq!(req.kind, EventKinds::Rename);
+        assert!(req.payload.is_none());
+        assert_eq!(touched(&req), vec![PathBuf::from("old"), PathBuf::from("new")]);
+    }
+
+    #[test]
+    fn test_synchronization_outside_workspace() {
+        let workspace = TempDir::new().unwrap();
+        let event = Event::new(EventKind::Remove(RemoveKind::File)).add_path(PathBuf::from("/etc/passwd"));
+
+        assert!(synchronization(workspace.path(), &event).is_none());
+    }
+}
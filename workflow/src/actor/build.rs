@@ -18,11 +18,13 @@ use super::DeployingState;
 use crate::errors::{Error, Result};
 use crate::{Context, Intent, State, Task};
 
-use amp_builder::{BuildDirector, KanikoBuilder, KpackBuilder};
+use amp_builder::{BuildDirector, BuildpacksEngine, KanikoBuilder, KpackBuilder, LifecycleBuilder};
 use amp_common::resource::{Actor, ActorState};
 use amp_common::schema::BuildMethod;
 
-use amp_resources::actor;
+use amp_resources::build::{self, BuildOutcome};
+use amp_resources::error::Error as ResourceError;
+use amp_resources::{actor, deployment};
 use async_trait::async_trait;
 use kube::runtime::controller::Action;
 use kube::ResourceExt;
@@ -41,7 +43,11 @@ impl State<Actor> for BuildingState {
         if task.matches(ctx) {
             match task.execute(ctx).await {
                 Ok(Some(intent)) => return Some(intent),
-                Err(err) => error!("Error during BuildTask execution: {}", err),
+                Err(err) => {
+                    error!("Error during BuildTask execution: {}", err);
+                    // Don't deploy the stale image, try to build it again later.
+                    return Some(Intent::Action(Action::requeue(Duration::from_secs(60))));
+                }
                 Ok(None) => {}
             }
         }
@@ -69,15 +75,22 @@ impl Task<Actor> for BuildTask {
         let build = actor.spec.character.build.clone().unwrap_or_default();
 
         // Generate `Builder` based on the build method
-        let builder = match build.method() {
+        let (builder, kind) = match build.method() {
             BuildMethod::Dockerfile => {
                 info!("Found dockerfile, build it with Kaniko");
-                BuildDirector::new(Box::new(KanikoBuilder::new(ctx.k8s.clone(), actor.clone())))
-            }
-            BuildMethod::Buildpacks => {
-                info!("Build the image with Cloud Native Buildpacks (kpack)");
-                BuildDirector::new(Box::new(KpackBuilder::new(ctx.k8s.clone(), actor.clone(), ctx.credentials.clone())))
+                (BuildDirector::new(Box::new(KanikoBuilder::new(ctx.k8s.clone(), actor.clone()))), "kaniko")
             }
+            BuildMethod::Buildpacks => match BuildpacksEngine::resolve(&actor.spec.character, ctx.buildpacks) {
+                BuildpacksEngine::Kpack => {
+                    info!("Build the image with Cloud Native Buildpacks (kpack)");
+                    let builder = KpackBuilder::new(ctx.k8s.clone(), actor.clone(), ctx.credentials.clone());
+                    (BuildDirector::new(Box::new(builder)), "kpack")
+                }
+                BuildpacksEngine::Lifecycle => {
+                    info!("Build the image with Cloud Native Buildpacks (lifecycle)");
+                    (BuildDirector::new(Box::new(LifecycleBuilder::new(ctx.k8s.clone(), actor.clone()))), "lifecycle")
+                }
+            },
         };
 
         // Prepare the build, initialize the some resources before building
@@ -85,8 +98,20 @@ impl Task<Actor> for BuildTask {
             return Ok(Some(Intent::Action(Action::requeue(duration))));
         }
 
-        // Build the image
+        // Build the image, and record it in the build history
         builder.build().await.map_err(Error::BuildError)?;
+        build::start(&ctx.k8s, actor, kind).await.map_err(Error::ResourceError)?;
+
+        // Stop here if the build failed, don't deploy the stale image.
+        if let Some(message) = builder.failed().await.map_err(Error::BuildError)? {
+            error!("Build of actor {} failed: {}", actor.name_any(), message);
+            build::finish(&ctx.k8s, actor, BuildOutcome::Failed, None, Some(message.clone()))
+                .await
+                .map_err(Error::ResourceError)?;
+            let condition = actor::build_failed(&message);
+            actor::patch_status(&ctx.k8s, &ctx.object, condition).await.map_err(Error::ResourceError)?;
+            return Ok(Some(Intent::Action(Action::await_change())));
+        }
 
         // Check if the build is completed and wait for it to finish.
         if !builder.completed().await.map_err(Error::BuildError)? {
@@ -94,6 +119,17 @@ impl Task<Actor> for BuildTask {
             return Ok(Some(Intent::Action(Action::requeue(Duration::from_secs(5)))));
         }
 
+        let digest = builder.digest().await.map_err(Error::BuildError)?;
+        build::finish(&ctx.k8s, actor, BuildOutcome::Succeeded, digest.clone(), None)
+            .await
+            .map_err(Error::ResourceError)?;
+        self.rollout(ctx, actor, digest).await.map_err(Error::ResourceError)?;
+
+        // The new image supersedes the one it's rolled back to.
+        actor::remove_annotation(&ctx.k8s, actor, actor::ROLLBACK_IMAGE_ANNOTATION)
+            .await
+            .map_err(Error::ResourceError)?;
+
         // Patch the status to running
         let condition = ActorState::running(true, "AutoRun", None);
         actor::patch_status(&ctx.k8s, &ctx.object, condition).await.map_err(Error::ResourceError)?;
@@ -101,3 +137,24 @@ impl Task<Actor> for BuildTask {
         Ok(None)
     }
 }
+
+impl BuildTask {
+    /// Make the new image visible to the Deployment, the tag of a rebuild is usually unchanged.
+    /// Pin the image by its digest, so that it's rolled out with the changed hash by `DeployTask`,
+    /// or restart the Deployment to pull the tag again if the builder doesn't know the digest.
+    async fn rollout(&self, ctx: &Context<Actor>, actor: &Actor, digest: Option<String>) -> Result<(), ResourceError> {
+        if let Some(digest) = digest {
+            let image = format!("{}@{}", actor.spec.image, digest);
+            return actor::annotate(&ctx.k8s, actor, actor::BUILT_IMAGE_ANNOTATION, &image).await;
+        }
+
+        actor::remove_annotation(&ctx.k8s, actor, actor::BUILT_IMAGE_ANNOTATION).await?;
+        let name = actor.name_any();
+        let namespace = actor.namespace().ok_or_else(|| ResourceError::MissingObjectKey(".metadata.namespace"))?;
+        if deployment::exists(&ctx.k8s, &namespace, &name).await? {
+            deployment::restart(&ctx.k8s, &namespace, &name).await?;
+        }
+
+        Ok(())
+    }
+}
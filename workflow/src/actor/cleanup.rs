@@ -16,11 +16,14 @@ use crate::errors::{Error, Result};
 use crate::{Context, Intent, State, Task};
 
 use amp_common::resource::Actor;
+use amp_resources::containers::syncer;
+use amp_resources::kpack::{cluster_builder, cluster_buildpack, cluster_store, References};
+use amp_resources::{actor, volume};
 
 use async_trait::async_trait;
 use k8s_openapi::api::core::v1::Namespace;
 use kube::{Api, ResourceExt};
-use tracing::{error, info, trace};
+use tracing::{debug, error, info, trace, warn};
 
 pub struct CleanupState;
 
@@ -66,6 +69,12 @@ impl Task<Actor> for CleanupTask {
 
 impl CleanupTask {
     async fn cleanup(&self, ctx: &Context<Actor>, actor: &Actor) -> Result<()> {
+        info!("Delete Actor `{}`", actor.name_any());
+
+        // The cluster-scoped objects and NATS consumers are not deleted with the namespace.
+        self.release_cluster_objects(ctx, actor).await?;
+        self.delete_consumer(ctx, actor).await;
+
         let namespace = actor.namespace().unwrap();
         let api: Api<Namespace> = Api::all((*ctx.k8s).clone());
 
@@ -76,8 +85,60 @@ impl CleanupTask {
             }
         }
 
-        info!("Delete Actor `{}`", actor.name_any());
+        if actor.spec.live {
+            volume::delete(&ctx.k8s, actor).await.map_err(Error::ResourceError)?;
+        }
 
         Ok(())
     }
+
+    /// Delete the kpack cluster objects which are not used by any other actors.
+    async fn release_cluster_objects(&self, ctx: &Context<Actor>, actor: &Actor) -> Result<()> {
+        let others: Vec<Actor> = actor::list_all(&ctx.k8s)
+            .await
+            .map_err(Error::ResourceError)?
+            .into_iter()
+            .filter(|other| other.uid() != actor.uid() && other.metadata.deletion_timestamp.is_none())
+            .collect();
+
+        let unused = References::from_actors([actor]).difference(&References::from_actors(&others));
+        if unused.is_empty() {
+            return Ok(());
+        }
+        info!("Release the unused cluster objects of Actor {}: {:?}", actor.name_any(), unused);
+
+        // Delete the builders first, they reference the stores and buildpacks.
+        for name in &unused.builders {
+            cluster_builder::delete(&ctx.k8s, name).await.map_err(Error::ResourceError)?;
+        }
+        for name in &unused.stores {
+            cluster_store::delete(&ctx.k8s, name).await.map_err(Error::ResourceError)?;
+        }
+        for name in &unused.buildpacks {
+            cluster_buildpack::delete(&ctx.k8s, name).await.map_err(Error::ResourceError)?;
+        }
+
+        Ok(())
+    }
+
+    /// Delete the durable NATS consumer of the actor's syncer, it's not fatal if failed.
+    async fn delete_consumer(&self, ctx: &Context<Actor>, actor: &Actor) {
+        let Ok(playbook) = syncer::owner_reference(actor) else {
+            return;
+        };
+
+        let stream = match ctx.jetstream.get_stream(&playbook).await {
+            Ok(stream) => stream,
+            Err(err) => {
+                debug!("The stream of playbook {} is not available: {}", playbook, err);
+                return;
+            }
+        };
+
+        let name = syncer::consumer_name(&actor.spec.name);
+        match stream.delete_consumer(&name).await {
+            Ok(_) => info!("Deleted the NATS consumer {} of playbook {}", name, playbook),
+            Err(err) => warn!("Failed to delete the NATS consumer {} of playbook {}: {}", name, playbook, err),
+        }
+    }
 }
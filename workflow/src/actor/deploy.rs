@@ -18,16 +18,19 @@ use crate::Intent;
 use crate::{Context, State, Task};
 
 use amp_common::resource::Actor;
-use amp_resources::containers::application;
-use amp_resources::deployment;
+use amp_resources::containers::devcontainer::{self, DevContainer};
+use amp_resources::containers::{application, syncer, workspace_claim_volume, workspace_volume};
 use amp_resources::error::Error as ResourceError;
-use amp_resources::hash;
+use amp_resources::hot_reload::HotReload;
+use amp_resources::kpack::BuildExt;
+use amp_resources::volume;
+use amp_resources::{actor, deployment, playbook};
 
 use async_trait::async_trait;
 use k8s_openapi::api::core::v1::PodSpec;
 use kube::ResourceExt;
 use tracing::trace;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use super::ExposingState;
 
@@ -80,16 +83,21 @@ impl DeployTask {
         let name = actor.name_any();
         let namespace = actor.namespace().ok_or_else(|| ResourceError::MissingObjectKey(".metadata.namespace"))?;
 
-        let resource = deployment::new(actor, self.pod(actor))?;
+        let mut resource = deployment::new(actor, self.pod(ctx, actor).await?)?;
         match deployment::exists(&ctx.k8s, &namespace, &name).await? {
             true => {
                 // Deployment already exists, update it if there are new changes
                 info!("Try to refresh an existing Deployment {name}");
-                let expected_hash = hash(&actor.spec)?;
+                let expected_hash = deployment::applied_hash(actor)?;
                 deployment::update(&ctx.k8s, &namespace, &name, resource, expected_hash).await?;
             }
             false => {
-                // Create a new Deployment
+                // Create a new Deployment, the one of a stopped playbook is suspended until it's started,
+                // e.g. the actor was still building when the playbook is stopped.
+                if self.stopped(ctx, actor).await? {
+                    info!("The playbook of Actor {name} is stopped, create its Deployment suspended");
+                    resource = deployment::suspended(resource);
+                }
                 deployment::create(&ctx.k8s, &namespace, resource).await?;
                 info!("Created new Deployment: {name}");
             }
@@ -98,7 +106,46 @@ impl DeployTask {
         Ok(())
     }
 
-    fn pod(&self, actor: &Actor) -> PodSpec {
-        PodSpec { containers: vec![application::container(&actor.spec)], ..Default::default() }
+    /// Check if the owning playbook of the actor is stopped.
+    async fn stopped(&self, ctx: &Context<Actor>, actor: &Actor) -> Result<bool, ResourceError> {
+        let playbook = playbook::get(&ctx.k8s, &syncer::owner_reference(actor)?).await?;
+        Ok(playbook::is_stopped(&playbook))
+    }
+
+    async fn pod(&self, ctx: &Context<Actor>, actor: &Actor) -> Result<PodSpec, ResourceError> {
+        // The workspace of live actor is kept in the PVC only if it's built by kpack.
+        let claimed = actor.spec.live && volume::exists(&ctx.k8s, actor).await?;
+
+        // Run the devcontainer in the development mode, with the synchronized workspace mounted.
+        if let Some(config) = DevContainer::from_actor(actor) {
+            let mut containers = vec![devcontainer::container(&actor.spec, &config)];
+            let mut volumes = devcontainer::volumes(&config);
+            if claimed {
+                volumes.push(workspace_claim_volume(actor.spec.character.pvc_name()));
+            } else {
+                // Nobody keeps the workspace, so synchronize it by a sidecar.
+                containers.push(syncer::container(actor, &None)?);
+                volumes.push(workspace_volume());
+            }
+            return Ok(PodSpec { containers, volumes: Some(volumes), ..Default::default() });
+        }
+
+        // The application runs the image it's rolled back to, until the next successful build.
+        let mut spec = actor.spec.clone();
+        spec.image = actor::deployed_image(actor).to_string();
+
+        match HotReload::from_spec(&spec) {
+            Some(config) if claimed => Ok(PodSpec {
+                containers: vec![application::dev_container(&spec, &config)],
+                volumes: Some(vec![workspace_claim_volume(actor.spec.character.pvc_name())]),
+                ..Default::default()
+            }),
+            hot_reload => {
+                if hot_reload.is_some() {
+                    warn!("The workspace volume of Actor {} is not found, fallback to rebuild mode", actor.name_any());
+                }
+                Ok(PodSpec { containers: vec![application::container(&spec)], ..Default::default() })
+            }
+        }
     }
 }
@@ -22,6 +22,8 @@ use amp_common::docker::{self, registry, DockerConfig};
 use amp_common::resource::{Actor, ActorState};
 
 use amp_resources::actor;
+use amp_resources::containers::devcontainer::{self, DevContainer, DEVCONTAINER_ANNOTATION};
+use amp_resources::error::Error as ResourceError;
 use async_trait::async_trait;
 use kube::runtime::controller::Action;
 use kube::ResourceExt;
@@ -75,8 +77,22 @@ impl Task<Actor> for InitTask {
     async fn execute(&self, ctx: &Context<Actor>) -> Result<Option<Intent<Actor>>> {
         let actor = &ctx.object;
 
+        // Load the devcontainer of the actor in the development mode, it runs the specified
+        // or default image without building, and fails if it's not supported.
+        let mut prebuilt = false;
+        if let Some(path) = devcontainer::path(&actor.spec) {
+            let config = self.load_devcontainer(ctx, &path).await?;
+            if let Some(message) = config.unsupported() {
+                error!("The devcontainer of actor {} can't be run: {}", actor.name_any(), message);
+                let condition = actor::devcontainer_unsupported(&message);
+                actor::patch_status(&ctx.k8s, &ctx.object, condition).await.map_err(Error::ResourceError)?;
+                return Ok(Some(Intent::Action(Action::await_change())));
+            }
+            prebuilt = true;
+        }
+
         // build if actor is live or the image is not built, else skip to next state
-        if actor.spec.live || !self.built(ctx).await? {
+        if !prebuilt && (actor.spec.live || !self.built(ctx).await?) {
             let condition = ActorState::building();
             actor::patch_status(&ctx.k8s, &ctx.object, condition).await.map_err(Error::ResourceError)?;
         } else {
@@ -91,6 +107,21 @@ impl Task<Actor> for InitTask {
 }
 
 impl InitTask {
+    /// Load the `devcontainer.json` from the character repository, and keep it in the annotation for deploying.
+    async fn load_devcontainer(&self, ctx: &Context<Actor>, path: &str) -> Result<DevContainer> {
+        let credentials = ctx.credentials.read().await;
+        let config = amp_resolver::load_devcontainer(&credentials, &ctx.object.spec.character, path)
+            .await
+            .map_err(Error::ResolveError)?;
+
+        let value = serde_json::to_string(&config)
+            .map_err(|err| Error::ResourceError(ResourceError::SerializationError(err)))?;
+        actor::annotate(&ctx.k8s, &ctx.object, DEVCONTAINER_ANNOTATION, &value).await.map_err(Error::ResourceError)?;
+        info!("Loaded the devcontainer of Actor {} from {}", ctx.object.name_any(), path);
+
+        Ok(config)
+    }
+
     /// Check if the image is already built
     async fn built(&self, ctx: &Context<Actor>) -> Result<bool> {
         let image = &ctx.object.spec.image;
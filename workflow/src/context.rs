@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use amp_builder::BuildpacksEngine;
 use amp_common::config::Credentials;
 use async_nats::jetstream;
 
@@ -24,4 +25,6 @@ pub struct Context<T> {
     pub k8s: Arc<kube::Client>,
     pub credentials: Arc<RwLock<Credentials>>,
     pub jetstream: Arc<jetstream::Context>,
+    /// The default engine to build images with buildpacks, if not chosen by the character.
+    pub buildpacks: BuildpacksEngine,
 }
@@ -25,7 +25,7 @@ use async_trait::async_trait;
 use kube::ResourceExt;
 use tracing::{debug, error, info, trace};
 
-use super::ResolvingState;
+use super::{ResolvingState, StartingState, StoppedState};
 
 pub struct InitialState;
 
@@ -45,6 +45,16 @@ impl State<Playbook> for InitialState {
             }
         }
 
+        // Transition to the stopped state if the playbook was stopped by user
+        if playbook::is_stopped(&ctx.object) {
+            return Some(Intent::State(Box::new(StoppedState)));
+        }
+
+        // Transition to the starting state if the playbook was started again by user
+        if playbook::is_starting(&ctx.object) {
+            return Some(Intent::State(Box::new(StartingState)));
+        }
+
         // Transition to the next state if needed
         Some(Intent::State(Box::new(ResolvingState)))
     }
@@ -24,6 +24,14 @@ mod run;
 pub use run::RunTask;
 pub use run::RunningState;
 
+mod stop;
+pub use stop::StopTask;
+pub use stop::StoppedState;
+
+mod start;
+pub use start::StartTask;
+pub use start::StartingState;
+
 mod cleanup;
 pub use cleanup::CleanupState;
 pub use cleanup::CleanupTask;
@@ -12,12 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
+
 use crate::errors::{Error, Result};
 use crate::{Context, Intent, State, Task};
 use amp_common::resource::Playbook;
 use amp_resolver::to_actor;
 use amp_resources::actor;
 use async_trait::async_trait;
+use kube::api::ListParams;
 use kube::ResourceExt;
 use tracing::{error, info, trace};
 
@@ -90,6 +93,18 @@ impl RunTask {
                 }
             }
         }
+
+        // Delete the actors whose characters were removed from the playbook
+        let namespace = playbook.spec.namespace();
+        let names: HashSet<&String> = characters.iter().map(|character| &character.meta.name).collect();
+        let actors = actor::list(&ctx.k8s, &namespace, &ListParams::default()).await.map_err(Error::ResourceError)?;
+        for item in actors {
+            if !names.contains(&item.name_any()) {
+                info!("Delete the removed Actor: {}", item.name_any());
+                actor::delete(&ctx.k8s, &namespace, &item.name_any()).await.map_err(Error::ResourceError)?;
+            }
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,85 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::errors::{Error, Result};
+use crate::{Context, Intent, State, Task};
+
+use amp_common::resource::{Playbook, PlaybookState};
+use amp_resources::{deployment, playbook};
+use async_trait::async_trait;
+use kube::ResourceExt;
+use tracing::{error, info, trace};
+
+use super::ResolvingState;
+
+pub struct StartingState;
+
+#[async_trait]
+impl State<Playbook> for StartingState {
+    /// Execute the logic for the starting state
+    async fn handle(&self, ctx: &Context<Playbook>) -> Option<Intent<Playbook>> {
+        trace!("Checking starting state of playbook {}", ctx.object.name_any());
+
+        // Check if StartTask should be executed
+        let task = StartTask::new();
+        if task.matches(ctx) {
+            match task.execute(ctx).await {
+                Ok(Some(intent)) => return Some(intent),
+                Err(err) => error!("Error during StartTask execution: {}", err),
+                Ok(None) => {}
+            }
+        }
+
+        // Transition to the next state if needed
+        Some(Intent::State(Box::new(ResolvingState)))
+    }
+}
+
+pub struct StartTask;
+
+#[async_trait]
+impl Task<Playbook> for StartTask {
+    fn new() -> Self {
+        StartTask
+    }
+
+    fn matches(&self, ctx: &Context<Playbook>) -> bool {
+        playbook::is_starting(&ctx.object)
+    }
+
+    /// Execute the task logic for StartTask using shared data
+    async fn execute(&self, ctx: &Context<Playbook>) -> Result<Option<Intent<Playbook>>> {
+        self.start(ctx, &ctx.object).await?;
+        Ok(None)
+    }
+}
+
+impl StartTask {
+    async fn start(&self, ctx: &Context<Playbook>, playbook: &Playbook) -> Result<()> {
+        let namespace = playbook.spec.namespace();
+
+        // Restore the replicas of all the actor deployments suspended by stopping.
+        let deployments = deployment::list(&ctx.k8s, &namespace).await.map_err(Error::ResourceError)?;
+        for resource in deployments.iter() {
+            deployment::resume(&ctx.k8s, &namespace, resource).await.map_err(Error::ResourceError)?;
+        }
+
+        // Resume the reconciliation from resolving, the playbook may be changed while stopped.
+        let condition = PlaybookState::resolving();
+        playbook::patch_status(&ctx.k8s, playbook, condition).await.map_err(Error::ResourceError)?;
+        info!("Started playbook {} again, Let's begin resolving", playbook.name_any());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+// Copyright (c) The Amphitheatre Authors. All rights reserved.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::errors::{Error, Result};
+use crate::{Context, Intent, State, Task};
+
+use amp_common::resource::Playbook;
+use amp_resources::{deployment, playbook};
+use async_trait::async_trait;
+use kube::ResourceExt;
+use tracing::{error, info, trace};
+
+pub struct StoppedState;
+
+#[async_trait]
+impl State<Playbook> for StoppedState {
+    /// Execute the logic for the stopped state
+    async fn handle(&self, ctx: &Context<Playbook>) -> Option<Intent<Playbook>> {
+        trace!("Checking stopped state of playbook {}", ctx.object.name_any());
+
+        // Check if StopTask should be executed
+        let task = StopTask::new();
+        if task.matches(ctx) {
+            match task.execute(ctx).await {
+                Ok(Some(intent)) => return Some(intent),
+                Err(err) => error!("Error during StopTask execution: {}", err),
+                Ok(None) => {}
+            }
+        }
+
+        None // No transition, wait for the playbook to be started again
+    }
+}
+
+pub struct StopTask;
+
+#[async_trait]
+impl Task<Playbook> for StopTask {
+    fn new() -> Self {
+        StopTask
+    }
+
+    fn matches(&self, ctx: &Context<Playbook>) -> bool {
+        playbook::is_stopped(&ctx.object)
+    }
+
+    /// Execute the task logic for StopTask using shared data
+    async fn execute(&self, ctx: &Context<Playbook>) -> Result<Option<Intent<Playbook>>> {
+        self.stop(ctx, &ctx.object).await?;
+        Ok(None)
+    }
+}
+
+impl StopTask {
+    async fn stop(&self, ctx: &Context<Playbook>, playbook: &Playbook) -> Result<()> {
+        let namespace = playbook.spec.namespace();
+
+        // Scale all the actor deployments down to zero,
+        // the previous replicas are kept on the deployment for starting again.
+        let deployments = deployment::list(&ctx.k8s, &namespace).await.map_err(Error::ResourceError)?;
+        for resource in deployments.iter() {
+            deployment::suspend(&ctx.k8s, &namespace, resource).await.map_err(Error::ResourceError)?;
+        }
+
+        info!("Stopped {} deployments of playbook {}", deployments.len(), playbook.name_any());
+
+        Ok(())
+    }
+}